@@ -0,0 +1,236 @@
+// Audit of a range of history, independent of the verification tag.
+//
+// Each commit is verified with the authorized keys in force when it was made:
+// the authorized keys file of its first parent. A root commit has no keys in force:
+// its own file would let any orphan commit trust itself, so it is verified with
+// the keys at `from`, or at the verification tag.
+// Keys are imported in a throwaway keyring per version of the file, so that
+// neither the keyring of the verifier nor later versions of the file are involved.
+
+use crate::authorized_keys::{AuthorizedKeys, import_authorized_keys};
+use crate::config::{AUTHORIZED_KEYS_FILE, Config, read_or_update_local_config};
+use crate::git::{check_tag_exists, find_commit, open_repo};
+use crate::gpg::{EphemeralHome, SignaturePolicy};
+use crate::verify::verify_commit;
+use git2::{Commit, Error as GitError, Oid};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::path::Path;
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditStatus {
+    Trusted,
+    Unsigned,
+    Untrusted,
+    /// No authorized keys file was in force for this commit.
+    NoAuthorizedKeys,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+    pub commit: String,
+    pub summary: String,
+    pub author: String,
+    /// Commit from which the authorized keys were read, none for a root commit
+    /// without `from` nor verification tag.
+    pub keys_commit: Option<String>,
+    pub status: AuditStatus,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditReport {
+    pub from: Option<String>,
+    pub to: String,
+    pub entries: Vec<AuditEntry>,
+}
+
+impl AuditReport {
+    pub fn is_trusted(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|entry| entry.status == AuditStatus::Trusted)
+    }
+}
+
+// Keyring holding a version of the authorized keys file
+struct KeySet {
+    // Removed with the context when dropped
+    _home: EphemeralHome,
+    gpg_ctx: gpgme::Context,
+    policy: SignaturePolicy,
+}
+
+impl KeySet {
+    fn new(content: &[u8], config: &Config) -> Result<KeySet, GitError> {
+        let home = EphemeralHome::new("audit").map_err(|e| GitError::from_str(&e))?;
+        let mut gpg_ctx = home.context();
+
+        let import_report = import_authorized_keys(&mut gpg_ctx, content);
+        for (line, reason) in &import_report.failures {
+            eprintln!(
+                "⚠️ Block at line {} of {} could not be imported: {}",
+                line.unwrap_or(1),
+                AUTHORIZED_KEYS_FILE,
+                reason
+            );
+        }
+
+        let mut policy = config.signature_policy.clone();
+        policy.authorized_subkeys = AuthorizedKeys::parse(content).authorized_subkeys;
+
+        Ok(KeySet {
+            _home: home,
+            gpg_ctx,
+            policy,
+        })
+    }
+}
+
+/// Audit commits reachable from `to` but not from `from` (the whole history without `from`).
+pub fn audit(repo_path: &str, from: Option<&str>, to: &str) -> Result<AuditReport, GitError> {
+    let repo = open_repo(repo_path);
+    let config = read_or_update_local_config(&repo, None)?;
+
+    let mut commits = repo.revwalk()?;
+    let to_commit = find_commit(&repo, to)?;
+    commits.push(to_commit.id())?;
+    let root_keys_commit = match from {
+        Some(from) => {
+            let from_commit = find_commit(&repo, from)?;
+            commits.hide(from_commit.id())?;
+            Some(from_commit)
+        }
+        None => match check_tag_exists(&repo) {
+            Some(tag_ref) => Some(tag_ref.peel_to_commit()?),
+            None => None,
+        },
+    };
+    commits.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+
+    // Blob id of the authorized keys file -> keyring
+    let mut key_sets: HashMap<Oid, KeySet> = HashMap::new();
+    let mut entries = Vec::new();
+
+    for oid in commits {
+        let commit = repo.find_commit(oid?)?;
+        let keys_commit = match commit.parent(0) {
+            Ok(parent) => Some(parent),
+            Err(_) => root_keys_commit.clone(),
+        };
+        let keys_blob = match &keys_commit {
+            Some(keys_commit) => authorized_keys_blob(keys_commit)?,
+            None => None,
+        };
+
+        let status = match keys_blob {
+            None => AuditStatus::NoAuthorizedKeys,
+            Some(blob_id) => {
+                let key_set = match key_sets.entry(blob_id) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        let content = repo.find_blob(blob_id)?.content().to_vec();
+                        entry.insert(KeySet::new(&content, &config)?)
+                    }
+                };
+
+                match verify_commit(&repo, &mut key_set.gpg_ctx, commit.id(), &key_set.policy) {
+                    Ok(true) => AuditStatus::Trusted,
+                    Ok(false) => AuditStatus::Untrusted,
+                    Err(_) => AuditStatus::Unsigned,
+                }
+            }
+        };
+
+        entries.push(AuditEntry {
+            commit: commit.id().to_string(),
+            summary: commit.summary().unwrap_or("").to_string(),
+            author: format!(
+                "{} <{}>",
+                commit.author().name().unwrap_or(""),
+                commit.author().email().unwrap_or("")
+            ),
+            keys_commit: keys_commit.map(|keys_commit| keys_commit.id().to_string()),
+            status,
+        });
+    }
+
+    Ok(AuditReport {
+        from: from.map(String::from),
+        to: to.to_string(),
+        entries,
+    })
+}
+
+// Versions of the file are identified by their blob id
+fn authorized_keys_blob(commit: &Commit) -> Result<Option<Oid>, GitError> {
+    match commit.tree()?.get_path(Path::new(AUTHORIZED_KEYS_FILE)) {
+        Ok(entry) if entry.kind() == Some(git2::ObjectType::Blob) => Ok(Some(entry.id())),
+        _ => Ok(None),
+    }
+}
+
+/// Audit a range of history and print a report, optionally written as JSON.
+/// Returns whether every commit is trusted.
+pub fn audit_command(
+    repo_path: &str,
+    from: Option<&str>,
+    to: &str,
+    report_path: Option<&str>,
+) -> Result<bool, GitError> {
+    let report = audit(repo_path, from, to)?;
+
+    let count = |status: AuditStatus| {
+        report
+            .entries
+            .iter()
+            .filter(|entry| entry.status == status)
+            .count()
+    };
+
+    println!(
+        "\n📋 Audit of {} commit(s) from {} to {}",
+        report.entries.len(),
+        from.unwrap_or("the first commit"),
+        to
+    );
+    println!("   ✅ {} trusted", count(AuditStatus::Trusted));
+
+    for (status, label) in [
+        (AuditStatus::Unsigned, "unsigned"),
+        (AuditStatus::Untrusted, "untrusted"),
+        (
+            AuditStatus::NoAuthorizedKeys,
+            "without authorized keys in force",
+        ),
+    ] {
+        let entries: Vec<&AuditEntry> = report
+            .entries
+            .iter()
+            .filter(|entry| entry.status == status)
+            .collect();
+
+        if entries.is_empty() {
+            continue;
+        }
+
+        println!("   🔴 {} {}:", entries.len(), label);
+        for entry in entries {
+            println!(
+                "      {} {} ({})",
+                entry.commit, entry.summary, entry.author
+            );
+        }
+    }
+
+    if let Some(path) = report_path {
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|e| GitError::from_str(&format!("JSON serialization failed: {}", e)))?;
+        std::fs::write(path, json)
+            .map_err(|e| GitError::from_str(&format!("Failed to write {}: {}", path, e)))?;
+        println!("\nReport written to {}", path);
+    }
+
+    Ok(report.is_trusted())
+}
@@ -0,0 +1,433 @@
+// Parsing of the authorized keys file.
+//
+// The file is a concatenation of armored public key blocks, with optional comments
+// (lines starting with `#`) which are ignored by GPG. A comment may hold a directive:
+//
+// # subkeys: <FINGERPRINT> [<FINGERPRINT>...]
+//
+// restricts the authorization of the certificates owning these subkeys
+// to the listed subkeys only. Other subkeys of these certificates are not authorized.
+
+use crate::config::AUTHORIZED_KEYS_FILE;
+use crate::git::get_file_content_from_commit;
+use git2::{Commit, Error as GitError, Repository};
+use serde::Serialize;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SUBKEYS_DIRECTIVE: &str = "subkeys:";
+
+const ARMOR_BEGIN: &str = "-----BEGIN ";
+const ARMOR_END: &str = "-----END ";
+const ARMOR_DASHES: &str = "-----";
+
+/// Armor type of the only blocks imported from the authorized keys file.
+pub const PUBLIC_KEY_BLOCK: &str = "PGP PUBLIC KEY BLOCK";
+
+#[derive(Debug, Default)]
+pub struct AuthorizedKeys {
+    /// Fingerprints of the only subkeys allowed to sign for their certificate.
+    pub authorized_subkeys: Vec<String>,
+    /// Armored blocks, in file order.
+    pub blocks: Vec<ArmoredBlock>,
+}
+
+/// Armored block of the authorized keys file.
+#[derive(Debug)]
+pub struct ArmoredBlock {
+    /// Armor type, e.g. `PGP PUBLIC KEY BLOCK`.
+    pub kind: String,
+    /// Line number of the BEGIN line, starting at 1.
+    pub line: usize,
+    /// Armored text, from BEGIN line to END line included.
+    pub text: String,
+    /// Whether the matching END line was found.
+    pub complete: bool,
+}
+
+impl AuthorizedKeys {
+    pub fn parse(content: &[u8]) -> AuthorizedKeys {
+        let content = String::from_utf8_lossy(content);
+        let mut authorized_keys = AuthorizedKeys::default();
+        let mut current_block: Option<ArmoredBlock> = None;
+
+        for (index, line) in content.lines().enumerate() {
+            if let Some(kind) = armor_header(line, ARMOR_BEGIN) {
+                // A new block starts before the previous one ended: it is truncated
+                if let Some(block) = current_block.take() {
+                    authorized_keys.blocks.push(block);
+                }
+
+                current_block = Some(ArmoredBlock {
+                    kind: kind.to_string(),
+                    line: index + 1,
+                    text: format!("{}\n", line.trim()),
+                    complete: false,
+                });
+                continue;
+            }
+
+            if let Some(mut block) = current_block.take() {
+                block.text.push_str(line.trim_end());
+                block.text.push('\n');
+
+                match armor_header(line, ARMOR_END) {
+                    Some(kind) => {
+                        block.complete = kind == block.kind;
+                        authorized_keys.blocks.push(block);
+                    }
+                    None => current_block = Some(block),
+                }
+                continue;
+            }
+
+            let Some(comment) = line.trim().strip_prefix('#') else {
+                continue;
+            };
+
+            if let Some(fingerprints) = comment.trim().strip_prefix(SUBKEYS_DIRECTIVE) {
+                authorized_keys.authorized_subkeys.extend(
+                    fingerprints
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .filter(|fpr| !fpr.is_empty())
+                        .map(|fpr| fpr.to_uppercase()),
+                );
+            }
+        }
+
+        if let Some(block) = current_block {
+            authorized_keys.blocks.push(block);
+        }
+
+        authorized_keys
+    }
+}
+
+// Armor type of a `-----BEGIN <kind>-----` or `-----END <kind>-----` line
+fn armor_header<'a>(line: &'a str, prefix: &str) -> Option<&'a str> {
+    line.trim()
+        .strip_prefix(prefix)
+        .and_then(|rest| rest.strip_suffix(ARMOR_DASHES))
+}
+
+// A certificate without any listed subkey authorizes all of its signing subkeys.
+pub fn is_subkey_authorized(
+    authorized_subkeys: &[String],
+    key: &gpgme::Key,
+    fingerprint: &str,
+) -> bool {
+    let restricted = key.subkeys().any(|subkey| {
+        subkey
+            .fingerprint()
+            .is_ok_and(|fpr| authorized_subkeys.contains(&fpr.to_uppercase()))
+    });
+
+    !restricted || authorized_subkeys.contains(&fingerprint.to_uppercase())
+}
+
+/// Public key of the authorized keys file, as seen by GPG.
+#[derive(Debug, Serialize)]
+pub struct KeyInfo {
+    pub fingerprint: String,
+    pub user_ids: Vec<String>,
+    pub created: Option<String>,
+    pub expires: Option<String>,
+    pub capabilities: String,
+    pub revoked: bool,
+    pub expired: bool,
+    /// Whether signatures of the primary key are trusted, given subkeys restrictions.
+    pub authorized: bool,
+    pub subkeys: Vec<SubkeyInfo>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubkeyInfo {
+    pub fingerprint: String,
+    pub algorithm: String,
+    pub created: Option<String>,
+    pub expires: Option<String>,
+    pub capabilities: String,
+    pub revoked: bool,
+    pub expired: bool,
+    /// Whether signatures of this subkey are trusted, given subkeys restrictions.
+    pub authorized: bool,
+}
+
+impl KeyInfo {
+    pub fn from_key(key: &gpgme::Key, authorized_subkeys: &[String]) -> KeyInfo {
+        let primary_key = key.primary_key();
+        let fingerprint = key.fingerprint().unwrap_or("unknown").to_string();
+
+        KeyInfo {
+            authorized: primary_key.as_ref().is_some_and(|subkey| subkey.can_sign())
+                && is_subkey_authorized(authorized_subkeys, key, &fingerprint),
+            fingerprint,
+            user_ids: key
+                .user_ids()
+                .map(|uid| uid.id().unwrap_or("").to_string())
+                .collect(),
+            created: primary_key
+                .as_ref()
+                .and_then(|subkey| subkey.creation_time())
+                .map(format_date),
+            expires: primary_key
+                .as_ref()
+                .and_then(|subkey| subkey.expiration_time())
+                .map(format_date),
+            capabilities: capabilities(
+                key.can_sign(),
+                key.can_certify(),
+                key.can_encrypt(),
+                key.can_authenticate(),
+            ),
+            revoked: key.is_revoked(),
+            expired: key.is_expired(),
+            subkeys: key
+                .subkeys()
+                .skip(1) // primary key
+                .map(|subkey| {
+                    let fingerprint = subkey.fingerprint().unwrap_or("unknown").to_string();
+
+                    SubkeyInfo {
+                        authorized: subkey.can_sign()
+                            && is_subkey_authorized(authorized_subkeys, key, &fingerprint),
+                        fingerprint,
+                        algorithm: subkey
+                            .algorithm_name()
+                            .unwrap_or_else(|_| subkey.algorithm().to_string()),
+                        created: subkey.creation_time().map(format_date),
+                        expires: subkey.expiration_time().map(format_date),
+                        capabilities: capabilities(
+                            subkey.can_sign(),
+                            subkey.can_certify(),
+                            subkey.can_encrypt(),
+                            subkey.can_authenticate(),
+                        ),
+                        revoked: subkey.is_revoked(),
+                        expired: subkey.is_expired(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    pub fn status(&self) -> &'static str {
+        key_status(self.revoked, self.expired)
+    }
+}
+
+impl SubkeyInfo {
+    pub fn status(&self) -> &'static str {
+        key_status(self.revoked, self.expired)
+    }
+}
+
+fn key_status(revoked: bool, expired: bool) -> &'static str {
+    if revoked {
+        "revoked"
+    } else if expired {
+        "expired"
+    } else {
+        "valid"
+    }
+}
+
+// Capabilities letters, as displayed by gpg
+fn capabilities(sign: bool, certify: bool, encrypt: bool, authenticate: bool) -> String {
+    [
+        (sign, 'S'),
+        (certify, 'C'),
+        (encrypt, 'E'),
+        (authenticate, 'A'),
+    ]
+    .iter()
+    .filter(|(enabled, _)| *enabled)
+    .map(|(_, letter)| *letter)
+    .collect()
+}
+
+/// Read the authorized keys file at a given commit.
+pub fn read_authorized_keys_file(repo: &Repository, commit: &Commit) -> Result<Vec<u8>, GitError> {
+    match get_file_content_from_commit(repo, commit, AUTHORIZED_KEYS_FILE)? {
+        Some(content) => Ok(content),
+        None => Err(GitError::from_str(&format!(
+            "File '{}' not found in commit {}. This commit cannot be verified.",
+            AUTHORIZED_KEYS_FILE,
+            commit.id()
+        ))),
+    }
+}
+
+/// Read the keys of an authorized keys file, without importing them in the keyring.
+pub fn read_key_infos(
+    gpg_ctx: &mut gpgme::Context,
+    content: &[u8],
+) -> Result<Vec<KeyInfo>, GitError> {
+    let authorized_keys = AuthorizedKeys::parse(content);
+    let data = gpgme::Data::from_bytes(content)
+        .map_err(|e| GitError::from_str(&format!("Failed to read authorized keys: {}", e)))?;
+
+    let keys = gpg_ctx
+        .read_keys(data)
+        .map_err(|e| GitError::from_str(&format!("Failed to read authorized keys: {}", e)))?;
+
+    keys.map(|key| {
+        key.map(|key| KeyInfo::from_key(&key, &authorized_keys.authorized_subkeys))
+            .map_err(|e| GitError::from_str(&format!("Invalid key in authorized keys: {}", e)))
+    })
+    .collect()
+}
+
+/// Outcome of the import of an authorized keys file into the keyring.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub considered: u32,
+    pub imported: u32,
+    pub unchanged: u32,
+    pub not_imported: u32,
+    pub secret: u32,
+    pub revoked: u32,
+    /// Fingerprints of the keys imported or already in the keyring.
+    pub fingerprints: Vec<String>,
+    /// Blocks which could not be imported: line of the block (none for the whole file) and reason.
+    pub failures: Vec<(Option<usize>, String)>,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} considered, {} imported, {} unchanged, {} not imported, {} secret, {} revoked",
+            self.considered,
+            self.imported,
+            self.unchanged,
+            self.not_imported,
+            self.secret,
+            self.revoked
+        )
+    }
+}
+
+/// Import an authorized keys file into the keyring, block by block,
+/// so that a broken block does not prevent the other keys from being imported.
+pub fn import_authorized_keys(gpg_ctx: &mut gpgme::Context, content: &[u8]) -> ImportReport {
+    let authorized_keys = AuthorizedKeys::parse(content);
+    let mut report = ImportReport::default();
+
+    if authorized_keys.blocks.is_empty() {
+        // Not armored: let GPG make sense of the whole file
+        import_data(gpg_ctx, content, None, &mut report);
+    }
+
+    for block in &authorized_keys.blocks {
+        // Secret keys must never end up in the keyring of the verifier
+        if block.kind != PUBLIC_KEY_BLOCK {
+            let reason = if block.kind.contains("PRIVATE KEY") {
+                "secret key material is never imported".to_string()
+            } else {
+                format!("unexpected armored block '{}'", block.kind)
+            };
+            report.failures.push((Some(block.line), reason));
+            continue;
+        }
+
+        import_data(
+            gpg_ctx,
+            block.text.as_bytes(),
+            Some(block.line),
+            &mut report,
+        );
+    }
+
+    report
+}
+
+fn import_data(
+    gpg_ctx: &mut gpgme::Context,
+    content: &[u8],
+    line: Option<usize>,
+    report: &mut ImportReport,
+) {
+    let result = match gpgme::Data::from_bytes(content).and_then(|data| gpg_ctx.import(data)) {
+        Ok(result) => result,
+        Err(e) => {
+            report.failures.push((line, e.to_string()));
+            return;
+        }
+    };
+
+    report.considered += result.considered();
+    report.imported += result.imported();
+    report.unchanged += result.unchanged();
+    report.not_imported += result.not_imported();
+    report.secret += result.secret_considered();
+    report.revoked += result.new_revocations();
+
+    // One failure per block, listing each key which could not be imported
+    let mut key_failures = Vec::new();
+    for import in result.imports() {
+        let fingerprint = import.fingerprint().unwrap_or("unknown").to_string();
+
+        match import.result() {
+            Ok(()) => {
+                if !report.fingerprints.contains(&fingerprint) {
+                    report.fingerprints.push(fingerprint);
+                }
+            }
+            Err(e) => key_failures.push(format!("key {}: {}", fingerprint, e)),
+        }
+    }
+
+    if !key_failures.is_empty() {
+        report.failures.push((line, key_failures.join(", ")));
+        return;
+    }
+
+    if result.considered() == 0 {
+        report.failures.push((line, "no key found".to_string()));
+    } else if result.not_imported() > 0 {
+        report.failures.push((
+            line,
+            format!("{} key(s) not imported", result.not_imported()),
+        ));
+    }
+}
+
+// Format a time as an UTC date (YYYY-MM-DD).
+// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+pub fn format_date(time: SystemTime) -> String {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+
+    let days = seconds.div_euclid(86400);
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// Format a time as an UTC date and time (YYYY-MM-DD HH:MM:SS UTC).
+pub fn format_datetime(time: SystemTime) -> String {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    }
+    .rem_euclid(86400);
+
+    format!(
+        "{} {:02}:{:02}:{:02} UTC",
+        format_date(time),
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
@@ -0,0 +1,252 @@
+// Cache of verified commits, shared by runs and branches.
+//
+// A commit is trusted for a given set of authorized keys and rules, identified by
+// a hash of the authorized keys file and of the policy: when either changes,
+// cached verifications no longer apply. Only trusted commits are cached,
+// one `<commit> <key set> [<signer>,...]` line each, in `.git/git-sign-verifier/verified`.
+//
+// Anyone able to write into `.git` could add lines: the file is signed by the verifier key
+// in `verified.sig`, and ignored when this signature is not made by that key, the one
+// signing the verification tag. Lines of other key sets are dropped when the cache is saved.
+
+use crate::git::{open_repo, sign_content, verifier_key};
+use crate::gpg::SignaturePolicy;
+use git2::{Error as GitError, ObjectType, Oid, Repository};
+use gpgme::Context;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CACHE_DIR: &str = "git-sign-verifier";
+const CACHE_FILE: &str = "verified";
+const SIGNATURE_FILE: &str = "verified.sig";
+
+/// Identify a set of authorized keys and the rules applied to their signatures:
+/// the hash of the authorized keys file followed by the policy, one field per line.
+///
+/// ```text
+/// rule=<any|all|N>
+/// hashes=<hash,...>
+/// min-rsa-bits=<N>
+/// curves=<curve,...>
+/// subkeys=<fingerprint,...>
+/// ```
+///
+/// Lists are sorted, so that the order of the configuration does not matter.
+pub fn key_set_id(authorized_keys_content: &[u8], policy: &SignaturePolicy) -> String {
+    let sorted = |values: &[String]| {
+        let mut values = values.to_vec();
+        values.sort();
+        values.join(",")
+    };
+
+    let mut data = authorized_keys_content.to_vec();
+    data.extend_from_slice(
+        format!(
+            "\nrule={}\nhashes={}\nmin-rsa-bits={}\ncurves={}\nsubkeys={}\n",
+            policy.rule,
+            sorted(&policy.algorithms.allowed_hashes),
+            policy.algorithms.min_rsa_bits,
+            sorted(&policy.algorithms.allowed_curves),
+            sorted(&policy.authorized_subkeys)
+        )
+        .as_bytes(),
+    );
+
+    Oid::hash_object(ObjectType::Blob, &data)
+        .map(|oid| oid.to_string())
+        .unwrap_or_default()
+}
+
+fn cache_path(repo: &Repository) -> PathBuf {
+    repo.path().join(CACHE_DIR).join(CACHE_FILE)
+}
+
+fn signature_path(repo: &Repository) -> PathBuf {
+    repo.path().join(CACHE_DIR).join(SIGNATURE_FILE)
+}
+
+/// Commits already verified with the key set of a policy.
+/// A policy without key set (keys not loaded from a file) disables the cache.
+pub struct VerificationCache {
+    path: PathBuf,
+    signature_path: PathBuf,
+    key_set: Option<String>,
+    // Lines of the cache file, once its signature is trusted
+    content: String,
+    // Trusted commits and the fingerprints of their signers
+    verified: HashMap<Oid, Vec<String>>,
+    added: Vec<Oid>,
+}
+
+impl VerificationCache {
+    pub fn load(
+        repo: &Repository,
+        gpg_ctx: &mut Context,
+        policy: &SignaturePolicy,
+    ) -> VerificationCache {
+        let path = cache_path(repo);
+        let signature_path = signature_path(repo);
+        let key_set = policy.key_set.clone();
+
+        let content = match &key_set {
+            Some(_) => read_signed_cache(&path, &signature_path, gpg_ctx),
+            None => String::new(),
+        };
+        let verified = match &key_set {
+            Some(key_set) => content
+                .lines()
+                .filter_map(|line| {
+                    let mut fields = line.split(' ');
+                    let commit = Oid::from_str(fields.next()?).ok()?;
+                    if fields.next()? != key_set {
+                        return None;
+                    }
+                    let signers = fields
+                        .next()
+                        .unwrap_or("")
+                        .split(',')
+                        .filter(|signer| !signer.is_empty())
+                        .map(String::from)
+                        .collect();
+                    Some((commit, signers))
+                })
+                .collect(),
+            None => HashMap::new(),
+        };
+
+        VerificationCache {
+            path,
+            signature_path,
+            key_set,
+            content,
+            verified,
+            added: Vec::new(),
+        }
+    }
+
+    /// Fingerprints of the signers of a cached commit, none when the commit is not cached.
+    pub fn signers(&self, oid: Oid) -> Option<&[String]> {
+        self.verified.get(&oid).map(Vec::as_slice)
+    }
+
+    pub fn add(&mut self, oid: Oid, signers: &[String]) {
+        if self.key_set.is_some() && !self.verified.contains_key(&oid) {
+            self.verified.insert(oid, signers.to_vec());
+            self.added.push(oid);
+        }
+    }
+
+    /// Append the commits added since the cache was loaded, and sign the cache again.
+    /// Lines of other key sets are dropped: they no longer apply.
+    pub fn save(&mut self, gpg_ctx: &mut Context) -> Result<(), GitError> {
+        let Some(key_set) = &self.key_set else {
+            return Ok(());
+        };
+        if self.added.is_empty() {
+            return Ok(());
+        }
+
+        let mut content: String = self
+            .content
+            .lines()
+            .filter(|line| line.split(' ').nth(1) == Some(key_set.as_str()))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        for oid in &self.added {
+            content.push_str(&format!(
+                "{} {} {}\n",
+                oid,
+                key_set,
+                self.verified[oid].join(",")
+            ));
+        }
+        let signature = sign_content(gpg_ctx, &content)
+            .map_err(|e| GitError::from_str(&format!("Failed to sign cache: {}", e)))?;
+
+        // A signature left over from a previous cache is never trusted for this one
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&self.path, &content)?;
+            fs::write(&self.signature_path, &signature)
+        };
+        write().map_err(|e| {
+            GitError::from_str(&format!(
+                "Failed to write cache {}: {}",
+                self.path.display(),
+                e
+            ))
+        })?;
+
+        self.content = content;
+        self.added.clear();
+        Ok(())
+    }
+}
+
+// Content of the cache file, when signed by the verifier key which signs the
+// verification tag: neither developer keys nor other secret keys of the keyring
+// can sign a forged cache.
+fn read_signed_cache(path: &Path, signature_path: &Path, gpg_ctx: &mut Context) -> String {
+    let Ok(content) = fs::read(path) else {
+        return String::new();
+    };
+
+    let verifier_fingerprints: Vec<String> = match verifier_key(gpg_ctx) {
+        Ok(key) => key
+            .subkeys()
+            .filter_map(|subkey| subkey.fingerprint().ok().map(String::from))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    let trusted = fs::read(signature_path).is_ok_and(|signature| {
+        match gpg_ctx.verify_detached(&signature, &content) {
+            Ok(result) => result
+                .signatures()
+                .filter(|sig| sig.status().is_ok())
+                .filter_map(|sig| sig.fingerprint().ok())
+                .any(|fingerprint| {
+                    verifier_fingerprints
+                        .iter()
+                        .any(|verifier| verifier.eq_ignore_ascii_case(fingerprint))
+                }),
+            Err(_) => false,
+        }
+    });
+
+    if !trusted {
+        eprintln!(
+            "⚠️ Verification cache {} is not signed by the verifier key: it is ignored",
+            path.display()
+        );
+        return String::new();
+    }
+
+    String::from_utf8(content).unwrap_or_default()
+}
+
+/// Invalidate all cached verifications.
+pub fn cache_clear_command(repo_path: &str) -> Result<(), GitError> {
+    let repo = open_repo(repo_path);
+    let path = cache_path(&repo);
+    let _ = fs::remove_file(signature_path(&repo));
+
+    match fs::remove_file(&path) {
+        Ok(()) => println!("🗑️ Verification cache {} cleared", path.display()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            println!("Verification cache is already empty")
+        }
+        Err(e) => {
+            return Err(GitError::from_str(&format!(
+                "Failed to remove cache {}: {}",
+                path.display(),
+                e
+            )));
+        }
+    }
+
+    Ok(())
+}
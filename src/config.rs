@@ -1,11 +1,30 @@
+use crate::gpg::{SignaturePolicy, SignatureRule};
 use git2::{Error as GitError, Repository};
 
 pub const TAG_NAME: &str = "SIGN_VERIFIED";
+// Per-branch verification tags are named after their branch: SIGN_VERIFIED-release/1.0
+pub const MARKER_PREFIX: &str = "SIGN_VERIFIED-";
 pub const AUTHORIZED_KEYS_FILE: &str = ".gpg_authorized_keys";
 pub const EXIT_INVALID_SIGNATURE: i32 = 127;
+// Previous tags are kept under this namespace when the tag is reset
+pub const RESETS_REFS: &str = "refs/git-sign-verifier/resets";
+// Fetched branches wait under this namespace until they are verified
+pub const QUARANTINE_REFS: &str = "refs/git-sign-verifier/quarantine";
+// Notes of verified commits: `git log --notes=signatures`
+pub const NOTES_REF: &str = "refs/notes/signatures";
 
+#[derive(Default)]
 pub struct Config {
     pub gpgme_home_dir: Option<String>,
+    pub signature_policy: SignaturePolicy,
+    /// Fail verification when a block of the authorized keys file cannot be imported.
+    pub strict_import: bool,
+    /// Require the tagger of a release tag to be a user ID of the key signing it.
+    pub check_tagger: bool,
+    /// Verify commits of submodules between their old and new gitlinks.
+    pub recurse_submodules: bool,
+    /// Record verified commits as notes, see `NOTES_REF`.
+    pub record_notes: bool,
 }
 
 pub fn read_or_update_local_config(
@@ -19,11 +38,73 @@ pub fn read_or_update_local_config(
 
     Ok(Config {
         gpgme_home_dir: resolved_gpgme_home_dir,
+        ..read_policy_config(repo)?
     })
 }
 
+// Configuration of the verification itself, without any keyring:
+// used with throwaway keyrings, e.g. in server hooks.
+pub fn read_policy_config(repo: &Repository) -> Result<Config, GitError> {
+    let repo_config = repo.config()?;
+
+    Ok(Config {
+        gpgme_home_dir: None,
+        signature_policy: read_signature_policy(&repo_config)?,
+        strict_import: repo_config
+            .get_bool("git-sign-verifier.strictimport")
+            .unwrap_or(false),
+        check_tagger: repo_config
+            .get_bool("git-sign-verifier.checktagger")
+            .unwrap_or(true),
+        recurse_submodules: repo_config
+            .get_bool("git-sign-verifier.recursesubmodules")
+            .unwrap_or(false),
+        record_notes: repo_config
+            .get_bool("git-sign-verifier.notes")
+            .unwrap_or(false),
+    })
+}
+
+// Signature policy is read from any config level,
+// so it can be enforced system-wide on a deployment server.
+fn read_signature_policy(config: &git2::Config) -> Result<SignaturePolicy, GitError> {
+    let mut policy = SignaturePolicy::default();
+
+    if let Ok(rule) = config.get_string("git-sign-verifier.signaturerule") {
+        policy.rule = rule
+            .parse::<SignatureRule>()
+            .map_err(|e| GitError::from_str(&e))?;
+    }
+
+    if let Ok(hashes) = config.get_string("git-sign-verifier.allowedhashes") {
+        policy.algorithms.allowed_hashes = split_list(&hashes);
+    }
+
+    if let Ok(bits) = config.get_i64("git-sign-verifier.minrsabits") {
+        policy.algorithms.min_rsa_bits = usize::try_from(bits).map_err(|_| {
+            GitError::from_str("git-sign-verifier.minrsabits must be a positive number")
+        })?;
+    }
+
+    if let Ok(curves) = config.get_string("git-sign-verifier.allowedcurves") {
+        policy.algorithms.allowed_curves = split_list(&curves);
+    }
+
+    Ok(policy)
+}
+
+// Comma or space separated list of values
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
 // gpgme_home_dir is provided as relative path for portability
-// but need to work as an absolute path.
+// but need to work as an absolute path. It is relative to the workdir,
+// or to the git dir of a bare repository.
 fn resolve_gpgme_home_dir(
     local_config: &mut git2::Config,
     gpgme_home_dir: Option<String>,
@@ -34,22 +115,17 @@ fn resolve_gpgme_home_dir(
             local_config
                 .set_str("git-sign-verifier.gpgmehomedir", &dir)
                 .unwrap();
-            abs_path(&repo, &dir)
+            abs_path(repo, &dir)
         }
         None => match local_config.get_string("git-sign-verifier.gpgmehomedir") {
-            Ok(dir) => abs_path(&repo, &dir),
+            Ok(dir) => abs_path(repo, &dir),
             Err(_) => None, // default home will be used
         },
     }
 }
 
 fn abs_path(repo: &Repository, dir: &str) -> Option<String> {
-    let abs_path = repo
-        .workdir()
-        .unwrap()
-        .join(dir)
-        .to_str()
-        .unwrap()
-        .to_string();
+    let base_dir = repo.workdir().unwrap_or_else(|| repo.path());
+    let abs_path = base_dir.join(dir).to_str().unwrap().to_string();
     Some(abs_path)
 }
@@ -0,0 +1,299 @@
+use crate::authorized_keys::read_key_infos;
+use crate::config::{AUTHORIZED_KEYS_FILE, TAG_NAME, read_or_update_local_config};
+use crate::git::{check_tag_exists, get_file_content_from_commit};
+use crate::gpg::create_gpg_context;
+use crate::verify::verify_tag;
+use git2::{Commit, Error as GitError, Repository};
+use gpgme::{Context, Protocol};
+use std::path::Path;
+use std::process::Command;
+
+// Maximum length of a unix socket path, including the trailing NUL byte
+const MAX_SOCKET_PATH_LENGTH: usize = 108;
+
+// Count of failed checks, printed with the fix of each problem
+#[derive(Default)]
+struct Diagnosis {
+    problems: usize,
+}
+
+impl Diagnosis {
+    fn ok(&self, message: &str) {
+        println!("✅ {}", message);
+    }
+
+    fn warn(&self, message: &str, fix: &str) {
+        println!("⚠️ {}\n   👉 {}", message, fix);
+    }
+
+    fn fail(&mut self, message: &str, fix: &str) {
+        self.problems += 1;
+        println!("🔴 {}\n   👉 {}", message, fix);
+    }
+}
+
+/// Check the preconditions of `init` and `verify`, printing a fix for each problem.
+/// Returns whether no problem was found.
+pub fn doctor_command(repo_path: &str) -> Result<bool, GitError> {
+    let mut diagnosis = Diagnosis::default();
+
+    let repo = match Repository::open(repo_path) {
+        Ok(repo) => {
+            diagnosis.ok(&format!("Repository {} found", repo_path));
+            repo
+        }
+        Err(e) => {
+            diagnosis.fail(
+                &format!("Cannot open repository {}: {}", repo_path, e.message()),
+                "Run the command from a git repository, or set --directory",
+            );
+            return Ok(false);
+        }
+    };
+
+    check_git_user(&repo, &mut diagnosis);
+
+    let config = match read_or_update_local_config(&repo, None) {
+        Ok(config) => config,
+        Err(e) => {
+            diagnosis.fail(
+                &format!("Invalid configuration: {}", e.message()),
+                "Fix the git-sign-verifier.* keys with `git config`",
+            );
+            return Ok(false);
+        }
+    };
+
+    let mut gpg_ctx = match Context::from_protocol(Protocol::OpenPgp) {
+        Ok(_) => create_gpg_context(&config),
+        Err(e) => {
+            diagnosis.fail(
+                &format!("GPGME cannot use the OpenPGP engine: {}", e),
+                "Install GnuPG 2 (gpg) and make sure it is in the PATH",
+            );
+            return Ok(false);
+        }
+    };
+
+    check_gpg_home(config.gpgme_home_dir.as_deref(), &mut diagnosis);
+    check_gpg_agent(config.gpgme_home_dir.as_deref(), &mut diagnosis);
+    check_secret_key(
+        &mut gpg_ctx,
+        config.gpgme_home_dir.as_deref(),
+        &mut diagnosis,
+    );
+
+    match repo.head().and_then(|head| head.peel_to_commit()) {
+        Ok(head) => check_authorized_keys(&repo, &head, "HEAD", &mut gpg_ctx, &mut diagnosis),
+        Err(e) => diagnosis.fail(
+            &format!("HEAD has no commit: {}", e.message()),
+            "Commit or checkout a branch first",
+        ),
+    }
+
+    match check_tag_exists(&repo) {
+        Some(tag_ref) => {
+            let tag_commit = tag_ref.peel_to_commit()?;
+            let tag_oid = tag_ref.target().unwrap();
+
+            if verify_tag(&repo, &mut gpg_ctx, tag_oid, &config.signature_policy)? {
+                diagnosis.ok(&format!("Tag {} signature is trusted", TAG_NAME));
+            } else {
+                diagnosis.fail(
+                    &format!("Tag {} signature is not trusted", TAG_NAME),
+                    "The keyring must hold the public key which signed the tag: check the GPG home",
+                );
+            }
+
+            check_authorized_keys(&repo, &tag_commit, TAG_NAME, &mut gpg_ctx, &mut diagnosis);
+        }
+        None => diagnosis.warn(
+            &format!("Tag {} doesn't exist: `verify` cannot run yet", TAG_NAME),
+            "Run `git-sign-verifier init`",
+        ),
+    }
+
+    if diagnosis.problems == 0 {
+        println!("\n🎉 No problem found.");
+    } else {
+        println!("\n🔴 {} problem(s) found.", diagnosis.problems);
+    }
+
+    Ok(diagnosis.problems == 0)
+}
+
+// The tag is signed with the identity of the local config
+fn check_git_user(repo: &Repository, diagnosis: &mut Diagnosis) {
+    let local_config = repo
+        .config()
+        .and_then(|config| config.open_level(git2::ConfigLevel::Local));
+
+    for (key, example) in [
+        ("user.name", "John Doe"),
+        ("user.email", "john.doe@example.com"),
+    ] {
+        match local_config.as_ref().map(|config| config.get_string(key)) {
+            Ok(Ok(value)) => diagnosis.ok(&format!("Local {} is {}", key, value)),
+            _ => diagnosis.fail(
+                &format!("Local {} is not set: the tag cannot be signed", key),
+                &format!("git config --local {} \"{}\"", key, example),
+            ),
+        }
+    }
+}
+
+fn check_gpg_home(home_dir: Option<&str>, diagnosis: &mut Diagnosis) {
+    let Some(home_dir) = home_dir else {
+        diagnosis.ok("GPG home: default keyring of the user");
+        return;
+    };
+
+    let path = Path::new(home_dir);
+    if !path.is_dir() {
+        diagnosis.fail(
+            &format!("GPG home {} doesn't exist", home_dir),
+            "Create it, or fix git-sign-verifier.gpgmehomedir (relative to the workdir, or to the git dir of a bare repository)",
+        );
+        return;
+    }
+
+    diagnosis.ok(&format!("GPG home: {}", home_dir));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        if let Ok(metadata) = std::fs::metadata(path)
+            && metadata.permissions().mode() & 0o077 != 0
+        {
+            diagnosis.warn(
+                &format!("GPG home {} is readable by other users", home_dir),
+                &format!("chmod 700 {}", home_dir),
+            );
+        }
+    }
+}
+
+// gpg-agent is required to sign the tag, and fails to start
+// when its socket path is too long.
+fn check_gpg_agent(home_dir: Option<&str>, diagnosis: &mut Diagnosis) {
+    match gpg_tool("gpgconf", home_dir, &["--list-dirs", "agent-socket"]) {
+        Ok(socket) => {
+            let socket = socket.trim();
+            if socket.len() >= MAX_SOCKET_PATH_LENGTH {
+                diagnosis.fail(
+                    &format!(
+                        "gpg-agent socket path is {} chars long, more than {} allowed: {}",
+                        socket.len(),
+                        MAX_SOCKET_PATH_LENGTH - 1,
+                        socket
+                    ),
+                    "Move the GPG home to a shorter path",
+                );
+                return;
+            }
+        }
+        Err(e) => diagnosis.warn(
+            &format!("Cannot locate gpg-agent socket: {}", e),
+            "Install gpgconf, shipped with GnuPG 2",
+        ),
+    }
+
+    match gpg_tool("gpg-connect-agent", home_dir, &["GETINFO version", "/bye"]) {
+        Ok(_) => diagnosis.ok("gpg-agent is reachable"),
+        Err(e) => diagnosis.fail(
+            &format!("gpg-agent is unreachable: {}", e),
+            "Check that gpg-agent can start: gpgconf --launch gpg-agent",
+        ),
+    }
+}
+
+// The tag is signed with the default secret key of the keyring
+fn check_secret_key(gpg_ctx: &mut Context, home_dir: Option<&str>, diagnosis: &mut Diagnosis) {
+    let signing_key = gpg_ctx.secret_keys().ok().and_then(|mut keys| {
+        keys.find_map(|key| {
+            key.ok()
+                .filter(|key| key.can_sign() && !key.is_revoked() && !key.is_expired())
+        })
+    });
+
+    match signing_key {
+        Some(key) => diagnosis.ok(&format!(
+            "Tag will be signed with secret key {}",
+            key.fingerprint().unwrap_or("unknown")
+        )),
+        None => diagnosis.fail(
+            "No usable secret key in the keyring: the tag cannot be signed",
+            &format!(
+                "Import a secret signing key: gpg{} --import secret-key.asc",
+                home_dir
+                    .map(|dir| format!(" --homedir {}", dir))
+                    .unwrap_or_default()
+            ),
+        ),
+    }
+}
+
+fn check_authorized_keys(
+    repo: &Repository,
+    commit: &Commit,
+    name: &str,
+    gpg_ctx: &mut Context,
+    diagnosis: &mut Diagnosis,
+) {
+    let content = match get_file_content_from_commit(repo, commit, AUTHORIZED_KEYS_FILE) {
+        Ok(Some(content)) => content,
+        _ => {
+            diagnosis.fail(
+                &format!("{} is missing at {}", AUTHORIZED_KEYS_FILE, name),
+                &format!(
+                    "Commit a {} file, see `git-sign-verifier keys build`",
+                    AUTHORIZED_KEYS_FILE
+                ),
+            );
+            return;
+        }
+    };
+
+    match read_key_infos(gpg_ctx, &content) {
+        Ok(keys) if keys.iter().any(|key| !key.revoked && !key.expired) => diagnosis.ok(&format!(
+            "{} at {} holds {} key(s)",
+            AUTHORIZED_KEYS_FILE,
+            name,
+            keys.len()
+        )),
+        Ok(_) => diagnosis.fail(
+            &format!("{} at {} holds no usable key", AUTHORIZED_KEYS_FILE, name),
+            "Add valid public keys, and check the file with `git-sign-verifier keys lint`",
+        ),
+        Err(e) => diagnosis.fail(
+            &format!("{} at {} is unreadable: {}", AUTHORIZED_KEYS_FILE, name, e),
+            "Check the file with `git-sign-verifier keys lint`",
+        ),
+    }
+}
+
+// Run a GnuPG tool on the GPG home, returns its standard output
+fn gpg_tool(program: &str, home_dir: Option<&str>, args: &[&str]) -> Result<String, String> {
+    let mut command = Command::new(program);
+    if let Some(dir) = home_dir {
+        command.args(["--homedir", dir]);
+    }
+
+    let output = command
+        .args(args)
+        .output()
+        .map_err(|e| format!("{} cannot run: {}", program, e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+
+    if !output.status.success() || stdout.lines().any(|line| line.starts_with("ERR")) {
+        return Err(format!(
+            "{} failed: {}{}",
+            program,
+            stdout.trim(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(stdout)
+}
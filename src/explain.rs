@@ -0,0 +1,144 @@
+use crate::authorized_keys::{KeyInfo, format_datetime, read_authorized_keys_file, read_key_infos};
+use crate::config::{AUTHORIZED_KEYS_FILE, read_or_update_local_config};
+use crate::git::{find_commit, open_repo, print_commit};
+use crate::gpg::{EphemeralHome, verify_gpg_signature_result};
+use crate::keys::resolve_keys_commit;
+use crate::verify::{load_authorized_keys, signed_commit_payload};
+use git2::Error as GitError;
+
+/// Explain in details the verification of a single commit:
+/// signed payload, signature packets, and which authorized key matched.
+/// Keys are read at the verification tag, or at `keys_at`, and imported into
+/// a temporary keyring: the revision may not be verified.
+/// Returns whether the commit would be trusted.
+pub fn explain_command(
+    repo_path: &str,
+    rev: &str,
+    keys_at: Option<&str>,
+) -> Result<bool, GitError> {
+    let repo = open_repo(repo_path);
+    let config = read_or_update_local_config(&repo, None)?;
+
+    let commit = find_commit(&repo, rev)?;
+    println!("🔍 Explaining commit:");
+    print_commit(&commit);
+
+    let Ok(signature_data) = commit.header_field_bytes("gpgsig") else {
+        println!("\n🔴 Commit is not signed: it has no gpgsig header");
+        return Ok(false);
+    };
+    let signature_str = signature_data.as_str().unwrap_or("");
+
+    let payload = signed_commit_payload(&commit);
+
+    println!("\n📄 Signed payload ({} bytes):", payload.len());
+    println!("{}", String::from_utf8_lossy(&payload));
+    println!("✍️ Signature:");
+    println!("{}", signature_str);
+
+    if !signature_str.starts_with("-----BEGIN PGP SIGNATURE-----") {
+        println!(
+            "\n🔴 Only GPG signatures are supported (first line is `{}`)",
+            signature_str.lines().next().unwrap_or("")
+        );
+        return Ok(false);
+    }
+
+    let keys_commit = resolve_keys_commit(&repo, keys_at)?;
+    println!(
+        "\n🔑 Authorized keys read from {} at commit {}",
+        AUTHORIZED_KEYS_FILE,
+        keys_commit.id()
+    );
+    let home = EphemeralHome::new("explain").map_err(|e| GitError::from_str(&e))?;
+    let mut gpg_ctx = home.context();
+    let authorized_keys = read_key_infos(
+        &mut gpg_ctx,
+        &read_authorized_keys_file(&repo, &keys_commit)?,
+    )?;
+    let policy = load_authorized_keys(&repo, &mut gpg_ctx, &keys_commit, &config)?;
+
+    let verification_result = gpg_ctx
+        .verify_detached(signature_str, payload.as_slice())
+        .map_err(|e| GitError::from_str(&format!("GPG verification failed: {}", e)))?;
+
+    for (index, sig) in verification_result.signatures().enumerate() {
+        let issuer = sig.fingerprint().unwrap_or("unknown");
+
+        println!("\n🔏 Signature #{}", index + 1);
+        println!("   issuer          {}", issuer);
+        println!("   key algorithm   {}", sig.key_algorithm());
+        println!("   hash algorithm  {}", sig.hash_algorithm());
+        println!(
+            "   created         {}",
+            sig.creation_time()
+                .map(format_datetime)
+                .unwrap_or_else(|| "unknown".to_string())
+        );
+        if let Some(expiration) = sig.expiration_time() {
+            println!("   expires         {}", format_datetime(expiration));
+        }
+        println!("   GPG summary     {:?}", sig.summary());
+        println!(
+            "   GPG status      {}",
+            match sig.status() {
+                Ok(()) => "good".to_string(),
+                Err(e) => e.to_string(),
+            }
+        );
+        println!("   {}", authorized_match(&authorized_keys, issuer));
+    }
+
+    println!("\nVerdict with signature rule `{}`:", policy.rule);
+    match verify_gpg_signature_result(verification_result, &mut gpg_ctx, &policy) {
+        Ok(_) => {
+            println!("✅ Commit {} would be trusted", commit.id());
+            Ok(true)
+        }
+        Err(e) => {
+            println!("🔴 Commit {} would be rejected: {}", commit.id(), e);
+            Ok(false)
+        }
+    }
+}
+
+// Which authorized key owns the signing key. The issuer may be a long key id
+// rather than a fingerprint, when GPG does not know the key.
+fn authorized_match(authorized_keys: &[KeyInfo], issuer: &str) -> String {
+    let issuer = issuer.to_uppercase();
+
+    for key in authorized_keys {
+        if key.fingerprint.ends_with(&issuer) {
+            return format!(
+                "matches primary key {} ({}) of {}",
+                key.fingerprint,
+                key.user_ids.join(", "),
+                AUTHORIZED_KEYS_FILE
+            );
+        }
+
+        if let Some(subkey) = key
+            .subkeys
+            .iter()
+            .find(|subkey| subkey.fingerprint.ends_with(&issuer))
+        {
+            return format!(
+                "matches subkey {} of key {} ({}) of {}{}",
+                subkey.fingerprint,
+                key.fingerprint,
+                key.user_ids.join(", "),
+                AUTHORIZED_KEYS_FILE,
+                if subkey.authorized {
+                    ""
+                } else {
+                    ", but this subkey is not authorized to sign"
+                }
+            );
+        }
+    }
+
+    format!(
+        "no key of {} matches: the signature cannot be trusted",
+        AUTHORIZED_KEYS_FILE
+    )
+}
@@ -0,0 +1,279 @@
+// Export of a verified commit for deployment.
+//
+// The tree is read from the object database, never from the working tree,
+// so that the exported bytes are exactly those of the signed commit.
+
+use crate::config::{TAG_NAME, read_or_update_local_config};
+use crate::git::{check_tag_exists, find_commit, open_repo};
+use crate::gpg::create_gpg_context;
+use crate::submodules::{open_submodule, verify_submodules};
+use crate::verify::{load_authorized_keys, verify_range, verify_tag};
+use git2::{Commit, Error as GitError, Oid, Repository, TreeWalkMode, TreeWalkResult};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Git file modes
+const MODE_TREE: i32 = 0o040000;
+const MODE_EXECUTABLE: i32 = 0o100755;
+const MODE_SYMLINK: i32 = 0o120000;
+const MODE_SUBMODULE: i32 = 0o160000;
+
+struct TreeEntry {
+    // Index of the submodule holding the entry, None for the exported repository
+    repo: Option<usize>,
+    path: PathBuf,
+    mode: i32,
+    id: Oid,
+}
+
+struct ExportedTree {
+    submodules: Vec<Repository>,
+    entries: Vec<TreeEntry>,
+}
+
+impl ExportedTree {
+    fn repo<'a>(&'a self, repo: &'a Repository, entry: &TreeEntry) -> &'a Repository {
+        entry.repo.map_or(repo, |index| &self.submodules[index])
+    }
+}
+
+/// Verify commits from the verification tag to `rev`, then write the tree of `rev`
+/// to `to`: a tarball when it ends with `.tar`, a new or empty directory otherwise.
+/// Submodules are exported with their tree when `git-sign-verifier.recursesubmodules`
+/// is enabled, once their commits are verified; otherwise a submodule fails the export.
+/// Nothing is written when a commit is not trusted.
+pub fn export_command(repo_path: &str, rev: &str, to: &str) -> Result<bool, GitError> {
+    let repo = open_repo(repo_path);
+    let config = read_or_update_local_config(&repo, None)?;
+    let mut gpg_ctx = create_gpg_context(&config);
+
+    let Some(from_ref) = check_tag_exists(&repo) else {
+        return Err(GitError::from_str(&format!(
+            "Tag {} doesn't exist!",
+            TAG_NAME
+        )));
+    };
+    if !verify_tag(
+        &repo,
+        &mut gpg_ctx,
+        from_ref.target().unwrap(),
+        &config.signature_policy,
+    )? {
+        return Ok(false);
+    }
+
+    let tag_commit = from_ref.peel_to_commit()?;
+    let policy = load_authorized_keys(&repo, &mut gpg_ctx, &tag_commit, &config)?;
+
+    let commit = find_commit(&repo, rev)?;
+    println!(
+        "Verifying commits from {}={} to {}={}",
+        TAG_NAME,
+        tag_commit.id(),
+        rev,
+        commit.id()
+    );
+    if !verify_range(
+        &repo,
+        from_ref.target().unwrap(),
+        commit.id(),
+        &mut gpg_ctx,
+        &policy,
+    )? {
+        eprintln!("🔴 {} is not trusted: nothing exported", rev);
+        return Ok(false);
+    }
+    if config.recurse_submodules
+        && !verify_submodules(
+            &repo,
+            Some(&tag_commit),
+            &commit,
+            &mut gpg_ctx,
+            &policy,
+            &config,
+        )?
+    {
+        eprintln!("🔴 Submodules of {} are not trusted: nothing exported", rev);
+        return Ok(false);
+    }
+
+    let tree = tree_entries(&repo, &commit, config.recurse_submodules)?;
+    if to.ends_with(".tar") {
+        export_tar(&repo, &commit, &tree, Path::new(to))?;
+    } else {
+        export_dir(&repo, &tree, Path::new(to))?;
+    }
+
+    println!("📦 Tree of {} exported to {}", commit.id(), to);
+
+    Ok(true)
+}
+
+// All entries of the commit tree, parents before children, followed by the
+// entries of its submodules when `recurse` is set. A submodule fails otherwise:
+// exporting the tree without it would deploy an incomplete tree.
+fn tree_entries(
+    repo: &Repository,
+    commit: &Commit,
+    recurse: bool,
+) -> Result<ExportedTree, GitError> {
+    let mut tree = ExportedTree {
+        submodules: Vec::new(),
+        entries: Vec::new(),
+    };
+    let mut pending = vec![(None, commit.id(), PathBuf::new())];
+
+    while let Some((index, id, prefix)) = pending.pop() {
+        let source = index.map_or(repo, |index: usize| &tree.submodules[index]);
+        let mut gitlinks = Vec::new();
+
+        source
+            .find_commit(id)?
+            .tree()?
+            .walk(TreeWalkMode::PreOrder, |root, entry| {
+                let path = Path::new(root).join(entry.name().unwrap_or(""));
+                if entry.filemode() == MODE_SUBMODULE {
+                    gitlinks.push((path.clone(), entry.id()));
+                }
+                tree.entries.push(TreeEntry {
+                    repo: index,
+                    path: prefix.join(path),
+                    mode: entry.filemode(),
+                    id: entry.id(),
+                });
+                TreeWalkResult::Ok
+            })?;
+
+        let mut opened = Vec::new();
+        for (path, id) in gitlinks {
+            let name = prefix.join(&path).display().to_string();
+            if !recurse {
+                return Err(GitError::from_str(&format!(
+                    "{} is a submodule: enable git-sign-verifier.recursesubmodules to verify and export it",
+                    name
+                )));
+            }
+            let Some(sub_repo) = open_submodule(source, &path) else {
+                return Err(GitError::from_str(&format!(
+                    "Submodule {} is not initialized: run `git submodule update --init`",
+                    name
+                )));
+            };
+            pending.push((
+                Some(tree.submodules.len() + opened.len()),
+                id,
+                prefix.join(&path),
+            ));
+            opened.push(sub_repo);
+        }
+        tree.submodules.extend(opened);
+    }
+
+    Ok(tree)
+}
+
+fn io_error(action: &str, path: &Path, e: std::io::Error) -> GitError {
+    GitError::from_str(&format!("Failed to {} {}: {}", action, path.display(), e))
+}
+
+fn export_dir(repo: &Repository, tree: &ExportedTree, to: &Path) -> Result<(), GitError> {
+    // Files already present would be mixed with the exported tree
+    if fs::read_dir(to).is_ok_and(|mut dir| dir.next().is_some()) {
+        return Err(GitError::from_str(&format!(
+            "{} is not empty",
+            to.display()
+        )));
+    }
+    fs::create_dir_all(to).map_err(|e| io_error("create", to, e))?;
+
+    for entry in &tree.entries {
+        let path = to.join(&entry.path);
+        let repo = tree.repo(repo, entry);
+
+        match entry.mode {
+            MODE_TREE | MODE_SUBMODULE => {
+                fs::create_dir_all(&path).map_err(|e| io_error("create", &path, e))?
+            }
+            MODE_SYMLINK => {
+                let blob = repo.find_blob(entry.id)?;
+                let target = String::from_utf8_lossy(blob.content()).to_string();
+
+                #[cfg(unix)]
+                std::os::unix::fs::symlink(&target, &path)
+                    .map_err(|e| io_error("create link", &path, e))?;
+                #[cfg(not(unix))]
+                fs::write(&path, target).map_err(|e| io_error("write", &path, e))?;
+            }
+            mode => {
+                let blob = repo.find_blob(entry.id)?;
+                fs::write(&path, blob.content()).map_err(|e| io_error("write", &path, e))?;
+
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    let permissions = if mode == MODE_EXECUTABLE {
+                        0o755
+                    } else {
+                        0o644
+                    };
+                    fs::set_permissions(&path, fs::Permissions::from_mode(permissions))
+                        .map_err(|e| io_error("set permissions of", &path, e))?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Entries are dated with the commit time, so that the tarball of a commit is reproducible
+fn export_tar(
+    repo: &Repository,
+    commit: &Commit,
+    tree: &ExportedTree,
+    to: &Path,
+) -> Result<(), GitError> {
+    let file = fs::File::create(to).map_err(|e| io_error("create", to, e))?;
+    let mut archive = tar::Builder::new(file);
+    let mtime = commit.time().seconds().max(0) as u64;
+
+    for entry in &tree.entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(mtime);
+        let repo = tree.repo(repo, entry);
+
+        let result = match entry.mode {
+            MODE_TREE | MODE_SUBMODULE => {
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_mode(0o755);
+                header.set_size(0);
+                archive.append_data(&mut header, &entry.path, std::io::empty())
+            }
+            MODE_SYMLINK => {
+                let blob = repo.find_blob(entry.id)?;
+                let target = String::from_utf8_lossy(blob.content()).to_string();
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_mode(0o777);
+                header.set_size(0);
+                archive.append_link(&mut header, &entry.path, target)
+            }
+            mode => {
+                let blob = repo.find_blob(entry.id)?;
+                header.set_entry_type(tar::EntryType::Regular);
+                header.set_mode(if mode == MODE_EXECUTABLE {
+                    0o755
+                } else {
+                    0o644
+                });
+                header.set_size(blob.content().len() as u64);
+                archive.append_data(&mut header, &entry.path, blob.content())
+            }
+        };
+        result.map_err(|e| io_error("archive", &entry.path, e))?;
+    }
+
+    archive
+        .into_inner()
+        .and_then(|mut file| std::io::Write::flush(&mut file))
+        .map_err(|e| io_error("write", to, e))
+}
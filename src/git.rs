@@ -1,6 +1,6 @@
-use crate::config::{TAG_NAME, read_or_update_local_config};
+use crate::config::{MARKER_PREFIX, TAG_NAME, read_or_update_local_config};
 use crate::gpg::create_gpg_context;
-use git2::{Commit, Error as GitError, Reference, Repository};
+use git2::{Commit, Error as GitError, Oid, Reference, Repository};
 use std::io::{Read, Seek};
 
 // Open a git repository
@@ -10,21 +10,23 @@ pub fn open_repo(repo_path: &str) -> Repository {
 }
 
 // Verify if a tag exists in a repository
-pub fn check_tag_exists<'a>(repo: &'a Repository) -> Option<Reference<'a>> {
-    match repo.find_reference(&format!("refs/tags/{}", TAG_NAME)) {
-        Ok(reference) => Some(reference),
-        Err(_) => None,
-    }
+pub fn check_tag_exists(repo: &Repository) -> Option<Reference<'_>> {
+    repo.find_reference(&format!("refs/tags/{}", TAG_NAME)).ok()
 }
 
 // Returns HEAD commit
-pub fn get_last_commit(repo: &Repository) -> Result<Commit, GitError> {
+pub fn get_last_commit(repo: &Repository) -> Result<Commit<'_>, GitError> {
     let head = repo.head()?;
     let commit = head.peel_to_commit()?;
 
     Ok(commit)
 }
 
+// Returns the commit of a revision (sha, branch, tag…)
+pub fn find_commit<'a>(repo: &'a Repository, rev: &str) -> Result<Commit<'a>, GitError> {
+    repo.revparse_single(rev)?.peel_to_commit()
+}
+
 /// Get file content from a specific commit
 pub fn get_file_content_from_commit(
     repo: &Repository,
@@ -47,7 +49,7 @@ pub fn get_file_content_from_commit(
 }
 
 // Pretty print a commit
-pub fn print_commit(commit: &Commit) -> () {
+pub fn print_commit(commit: &Commit) {
     println!("  commit {}", commit.id());
     println!(
         "  author: {} <{}>",
@@ -57,8 +59,24 @@ pub fn print_commit(commit: &Commit) -> () {
     println!("\n  {}", commit.message().unwrap_or("no msg"));
 }
 
-// Add a tag on a commit using a tagger config
-pub fn add_tag(repo: &Repository, commit: &Commit) -> Result<(), GitError> {
+// Add the verification tag on a commit using a tagger config.
+// Details are appended to the tag message.
+pub fn add_tag(repo: &Repository, commit: &Commit, details: Option<&str>) -> Result<(), GitError> {
+    add_named_tag(repo, TAG_NAME, commit, details)
+}
+
+// Name of the tag marking the last verified commit of a branch
+pub fn marker_tag_name(branch: &str) -> String {
+    format!("{}{}", MARKER_PREFIX, branch)
+}
+
+// Add a signed tag with any name, see `add_tag`
+pub fn add_named_tag(
+    repo: &Repository,
+    tag_name: &str,
+    commit: &Commit,
+    details: Option<&str>,
+) -> Result<(), GitError> {
     let user = read_user(repo)?;
 
     let tagger = git2::Signature::now(&user.name, &user.email)?;
@@ -68,22 +86,26 @@ pub fn add_tag(repo: &Repository, commit: &Commit) -> Result<(), GitError> {
     let mut gpg_ctx = create_gpg_context(&config);
 
     let base_message = "Verification tag managed by git-sign-verifier";
+    let message = match details {
+        Some(details) => format!("{}\n\n{}", base_message, details.trim_end()),
+        None => base_message.to_string(),
+    };
 
     // Create the tag content that Git expects for signing
     // // TIP: git cat-file -p SIGNED_TAG outputs a raw tag content with signature
     let tag_content = format!(
         "object {}\ntype commit\ntag {}\ntagger {} <{}> {} {:+05}\n\n{}\n",
         commit.id(),
-        TAG_NAME,
+        tag_name,
         tagger.name().unwrap_or(""),
         tagger.email().unwrap_or(""),
         tagger.when().seconds(),
         tagger.when().offset_minutes() * 100 / 60, // format is +0200
-        base_message
+        message
     );
 
     // Sign the tag content
-    let signature = match sign_tag_content(&mut gpg_ctx, &tag_content) {
+    let signature = match sign_content(&mut gpg_ctx, &tag_content) {
         Ok(sig) => sig,
         Err(e) => {
             eprintln!("⚠️ Failed to sign tag content: {}", e);
@@ -101,7 +123,7 @@ pub fn add_tag(repo: &Repository, commit: &Commit) -> Result<(), GitError> {
 
     // Create the reference to the tag
     repo.reference(
-        &format!("refs/tags/{}", TAG_NAME),
+        &format!("refs/tags/{}", tag_name),
         tag_oid,
         true, // overwrite
         &format!("{} on {}", base_message, commit.id()),
@@ -110,6 +132,45 @@ pub fn add_tag(repo: &Repository, commit: &Commit) -> Result<(), GitError> {
     Ok(())
 }
 
+// Identity of the verifier, from the local config, as used by the tag
+pub fn verifier_signature(repo: &Repository) -> Result<git2::Signature<'static>, GitError> {
+    let user = read_user(repo)?;
+
+    git2::Signature::now(&user.name, &user.email)
+}
+
+// Point a ref to a new commit signed like the verification tag, e.g. for notes
+pub fn add_signed_commit(
+    repo: &Repository,
+    ref_name: &str,
+    tree: &git2::Tree,
+    parents: &[&Commit],
+    message: &str,
+) -> Result<Oid, GitError> {
+    let author = verifier_signature(repo)?;
+
+    let config = read_or_update_local_config(repo, None)?;
+    let mut gpg_ctx = create_gpg_context(&config);
+
+    let buffer = repo.commit_create_buffer(&author, &author, message, tree, parents)?;
+    let content = buffer
+        .as_str()
+        .ok_or_else(|| GitError::from_str("Invalid UTF-8 in commit"))?;
+
+    let signature = match sign_content(&mut gpg_ctx, content) {
+        Ok(sig) => sig,
+        Err(e) => {
+            eprintln!("⚠️ Failed to sign commit content: {}", e);
+            return Err(GitError::from_str("Failed to sign commit"));
+        }
+    };
+
+    let oid = repo.commit_signed(content, &signature, None)?;
+    repo.reference(ref_name, oid, true, message)?;
+
+    Ok(oid)
+}
+
 struct GitUser {
     name: String,
     email: String,
@@ -125,8 +186,23 @@ fn read_user(repo: &Repository) -> Result<GitUser, GitError> {
     Ok(GitUser { name, email })
 }
 
-// Sign tag content with GPG
-fn sign_tag_content(gpg_ctx: &mut gpgme::Context, content: &str) -> Result<String, gpgme::Error> {
+/// Key of the verifier, signing the verification tag, notes and cache:
+/// the first secret key of the keyring able to sign, as GPG picks without `default-key`.
+pub fn verifier_key(gpg_ctx: &mut gpgme::Context) -> Result<gpgme::Key, gpgme::Error> {
+    gpg_ctx
+        .secret_keys()?
+        .filter_map(Result::ok)
+        .find(|key| key.can_sign() && !key.is_bad())
+        .ok_or(gpgme::Error::NO_SECKEY)
+}
+
+/// Sign content with the verifier key, e.g. a tag or a commit.
+/// Returns the armored detached signature.
+pub fn sign_content(gpg_ctx: &mut gpgme::Context, content: &str) -> Result<String, gpgme::Error> {
+    let key = verifier_key(gpg_ctx)?;
+    gpg_ctx.clear_signers();
+    gpg_ctx.add_signer(&key)?;
+
     // Create data for signing
     let content_data = gpgme::Data::from_bytes(content.as_bytes())?;
     let mut signature_data = gpgme::Data::new()?;
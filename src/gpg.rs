@@ -1,5 +1,12 @@
+use crate::authorized_keys::is_subkey_authorized;
 use crate::config::Config;
 use gpgme::{Context, Protocol, SignatureSummary, VerificationResult};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 // Initialize a GPG verification context
 pub fn create_gpg_context(config: &Config) -> gpgme::Context {
@@ -10,54 +17,471 @@ pub fn create_gpg_context(config: &Config) -> gpgme::Context {
         }
     };
 
-    if let Some(home_dir) = config.gpgme_home_dir.as_ref() {
-        if let Err(e) = gpg_ctx.set_engine_home_dir(home_dir.as_str()) {
-            panic!("Error setting GPGME home directory: {}", e);
-        }
+    if let Some(home_dir) = config.gpgme_home_dir.as_ref()
+        && let Err(e) = gpg_ctx.set_engine_home_dir(home_dir.as_str())
+    {
+        panic!("Error setting GPGME home directory: {}", e);
     }
 
     gpg_ctx
 }
 
-// Verify a message has been signed by a known key.
-// A single valid is enough so
-// we have to ignore errors on any other signature
-// until we eventually find a signature from a known key.
-//
-// See https://github.com/gpg-rs/gpgme/blob/master/examples/verify.rs
-pub fn verify_gpg_signature_result(
-    verification_result: VerificationResult,
-) -> Result<(), &'static str> {
-    let mut errors = Vec::new();
+/// Temporary GPG home, removed with its daemons when dropped.
+/// Used to work on keys without touching the keyring of the verifier.
+pub struct EphemeralHome {
+    pub path: PathBuf,
+}
+
+impl EphemeralHome {
+    // Note: full path length of gpg agent socket must be limited to 108 chars.
+    pub fn new(name: &str) -> Result<EphemeralHome, String> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let path =
+            std::env::temp_dir().join(format!("gsv-{}-{}-{}", name, std::process::id(), nanos));
+
+        fs::create_dir_all(&path)
+            .map_err(|e| format!("Failed to create GPG home {}: {}", path.display(), e))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o700))
+                .map_err(|e| format!("Failed to secure GPG home {}: {}", path.display(), e))?;
+        }
+
+        Ok(EphemeralHome { path })
+    }
 
-    for sig in verification_result.signatures() {
-        let fingerprint = sig.fingerprint().unwrap();
-        println!("   Verify key {}", fingerprint);
+    pub fn context(&self) -> Context {
+        create_gpg_context(&Config {
+            gpgme_home_dir: Some(self.path.to_string_lossy().to_string()),
+            ..Config::default()
+        })
+    }
+}
+
+impl Drop for EphemeralHome {
+    fn drop(&mut self) {
+        let _ = Command::new("gpgconf")
+            .arg("--homedir")
+            .arg(&self.path)
+            .args(["--kill", "all"])
+            .output();
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// How many signatures of a single object must be valid for the object to be trusted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureRule {
+    /// At least one valid signature.
+    Any,
+    /// Every signature must be valid.
+    All,
+    /// At least N valid signatures.
+    AtLeast(usize),
+}
+
+impl FromStr for SignatureRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "any" => Ok(SignatureRule::Any),
+            "all" => Ok(SignatureRule::All),
+            other => match other.parse::<usize>() {
+                Ok(n) if n > 0 => Ok(SignatureRule::AtLeast(n)),
+                _ => Err(format!(
+                    "Invalid signature rule `{}`: expected `any`, `all` or a positive number",
+                    s
+                )),
+            },
+        }
+    }
+}
 
-        if sig.summary().contains(SignatureSummary::KEY_REVOKED) {
-            errors.push("GPG key revoked");
+impl fmt::Display for SignatureRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureRule::Any => write!(f, "any"),
+            SignatureRule::All => write!(f, "all"),
+            SignatureRule::AtLeast(n) => write!(f, "{}", n),
         }
+    }
+}
+
+/// Minimal cryptographic strength required from signatures and signing keys.
+#[derive(Clone, Debug)]
+pub struct AlgorithmPolicy {
+    /// Hash algorithm names, as reported by GPG (`SHA256`…).
+    pub allowed_hashes: Vec<String>,
+    /// Minimum size of RSA keys, also applied to DSA and Elgamal keys.
+    pub min_rsa_bits: usize,
+    /// Curve names of elliptic curve keys, as reported by GPG (`ed25519`…).
+    pub allowed_curves: Vec<String>,
+}
 
-        if sig.summary().contains(SignatureSummary::KEY_EXPIRED) {
-            errors.push("GPG key expired");
+impl Default for AlgorithmPolicy {
+    fn default() -> Self {
+        AlgorithmPolicy {
+            allowed_hashes: ["SHA256", "SHA384", "SHA512", "SHA224"]
+                .map(String::from)
+                .to_vec(),
+            min_rsa_bits: 2048,
+            allowed_curves: ["ed25519", "ed448", "nistp256", "nistp384", "nistp521"]
+                .map(String::from)
+                .to_vec(),
         }
+    }
+}
 
-        if sig.summary().contains(SignatureSummary::SIG_EXPIRED) {
-            errors.push("Signature expired");
+impl AlgorithmPolicy {
+    pub fn check_hash(&self, hash: gpgme::HashAlgorithm) -> Result<(), String> {
+        let name = hash.name().unwrap_or("unknown");
+
+        if self
+            .allowed_hashes
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(name))
+        {
+            Ok(())
+        } else {
+            Err(format!("hash algorithm {} is not allowed", name))
         }
+    }
+
+    pub fn check_subkey(&self, subkey: &gpgme::Subkey) -> Result<(), String> {
+        let fingerprint = subkey.fingerprint().unwrap_or("unknown");
 
-        if sig.summary().contains(SignatureSummary::KEY_MISSING) {
-            errors.push("Unknown GPG key, missing in keyring");
+        match subkey.algorithm() {
+            gpgme::KeyAlgorithm::Rsa
+            | gpgme::KeyAlgorithm::RsaSign
+            | gpgme::KeyAlgorithm::RsaEncrypt
+            | gpgme::KeyAlgorithm::Dsa
+            | gpgme::KeyAlgorithm::Elgamal
+            | gpgme::KeyAlgorithm::ElgamalEncrypt => {
+                if subkey.length() >= self.min_rsa_bits {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "key {} is {} {} bits, at least {} bits required",
+                        fingerprint,
+                        subkey.algorithm(),
+                        subkey.length(),
+                        self.min_rsa_bits
+                    ))
+                }
+            }
+            gpgme::KeyAlgorithm::Ecc
+            | gpgme::KeyAlgorithm::Ecdsa
+            | gpgme::KeyAlgorithm::Eddsa
+            | gpgme::KeyAlgorithm::Ecdh => {
+                let curve = subkey.curve().unwrap_or("unknown");
+
+                if self
+                    .allowed_curves
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(curve))
+                {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "key {} uses curve {} which is not allowed",
+                        fingerprint, curve
+                    ))
+                }
+            }
+            algorithm => Err(format!(
+                "key {} uses unsupported algorithm {}",
+                fingerprint, algorithm
+            )),
         }
+    }
+
+    // Only the primary key (which certifies subkeys) and signing subkeys matter:
+    // encryption subkeys are never used to verify anything.
+    pub fn check_key(&self, key: &gpgme::Key) -> Vec<String> {
+        key.subkeys()
+            .enumerate()
+            .filter(|(index, subkey)| *index == 0 || subkey.can_sign())
+            .filter_map(|(_, subkey)| self.check_subkey(&subkey).err())
+            .collect()
+    }
+}
+
+/// Rules a signed object must satisfy to be trusted.
+#[derive(Clone, Debug)]
+pub struct SignaturePolicy {
+    pub rule: SignatureRule,
+    pub algorithms: AlgorithmPolicy,
+    /// Uppercased fingerprints of subkeys restricting the authorization of their certificate.
+    pub authorized_subkeys: Vec<String>,
+    /// Identifies the authorized keys and rules the policy was loaded with, see `cache`.
+    pub key_set: Option<String>,
+}
 
-        if errors.is_empty() {
-            return Ok(());
+impl Default for SignaturePolicy {
+    fn default() -> Self {
+        SignaturePolicy {
+            rule: SignatureRule::Any,
+            algorithms: AlgorithmPolicy::default(),
+            authorized_subkeys: Vec::new(),
+            key_set: None,
         }
     }
+}
+
+/// Verdict on a single signature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignatureVerdict {
+    /// Cryptographically valid signature from a known, usable key.
+    Good,
+    /// The signed payload does not match the signature: it has been tampered with.
+    Bad,
+    /// The signing key is neither in the keyring nor in the authorized keys.
+    UnknownKey,
+    Revoked,
+    KeyExpired,
+    SignatureExpired,
+    /// The signature or its key relies on an algorithm considered too weak.
+    WeakAlgorithm(String),
+    /// The signing subkey is not among the subkeys authorized for its certificate.
+    UnauthorizedSubkey,
+    /// Any other verification failure reported by GPG.
+    Error(String),
+}
+
+impl SignatureVerdict {
+    pub fn is_good(&self) -> bool {
+        *self == SignatureVerdict::Good
+    }
+}
 
-    if errors.is_empty() {
-        Err("No signature found")
+impl fmt::Display for SignatureVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureVerdict::Good => write!(f, "Good signature"),
+            SignatureVerdict::Bad => write!(f, "Bad signature, signed content has been altered"),
+            SignatureVerdict::UnknownKey => write!(f, "Unknown GPG key, missing in keyring"),
+            SignatureVerdict::Revoked => write!(f, "GPG key revoked"),
+            SignatureVerdict::KeyExpired => write!(f, "GPG key expired"),
+            SignatureVerdict::SignatureExpired => write!(f, "Signature expired"),
+            SignatureVerdict::WeakAlgorithm(detail) => write!(f, "Weak algorithm: {}", detail),
+            SignatureVerdict::UnauthorizedSubkey => {
+                write!(f, "Subkey not authorized for its certificate")
+            }
+            SignatureVerdict::Error(e) => write!(f, "Verification error: {}", e),
+        }
+    }
+}
+
+/// Outcome of the verification of a single signature.
+#[derive(Clone, Debug)]
+pub struct SignatureCheck {
+    /// Fingerprint of the signing (sub)key
+    pub fingerprint: String,
+    /// Fingerprint of the certificate owning the signing key, when known
+    pub primary_fingerprint: Option<String>,
+    pub verdict: SignatureVerdict,
+    pub validity: gpgme::Validity,
+}
+
+impl SignatureCheck {
+    pub fn is_valid(&self) -> bool {
+        self.verdict.is_good()
+    }
+
+    // Human description of the signing key, telling which subkey signed.
+    pub fn signer(&self) -> String {
+        match &self.primary_fingerprint {
+            Some(primary) if *primary != self.fingerprint => {
+                format!("subkey {} of key {}", self.fingerprint, primary)
+            }
+            _ => format!("key {}", self.fingerprint),
+        }
+    }
+}
+
+fn has_status(sig: &gpgme::Signature, error: gpgme::Error) -> bool {
+    match sig.status() {
+        Ok(()) => false,
+        Err(e) => e.code() == error.code(),
+    }
+}
+
+// Map GPG summary flags and status to a verdict.
+// Order matters: a revoked key also raises RED, and a missing key
+// prevents any other check, so the most specific reason wins.
+fn signature_verdict(sig: &gpgme::Signature) -> SignatureVerdict {
+    let summary = sig.summary();
+
+    if summary.contains(SignatureSummary::KEY_MISSING) || has_status(sig, gpgme::Error::NO_PUBKEY) {
+        SignatureVerdict::UnknownKey
+    } else if summary.contains(SignatureSummary::KEY_REVOKED)
+        || has_status(sig, gpgme::Error::CERT_REVOKED)
+    {
+        SignatureVerdict::Revoked
+    } else if has_status(sig, gpgme::Error::BAD_SIGNATURE) {
+        SignatureVerdict::Bad
+    } else if summary.contains(SignatureSummary::KEY_EXPIRED)
+        || has_status(sig, gpgme::Error::KEY_EXPIRED)
+    {
+        SignatureVerdict::KeyExpired
+    } else if summary.contains(SignatureSummary::SIG_EXPIRED)
+        || has_status(sig, gpgme::Error::SIG_EXPIRED)
+    {
+        SignatureVerdict::SignatureExpired
+    } else if has_status(sig, gpgme::Error::DIGEST_ALGO)
+        || has_status(sig, gpgme::Error::PUBKEY_ALGO)
+    {
+        SignatureVerdict::WeakAlgorithm(format!(
+            "{} with {} rejected by GPG",
+            sig.key_algorithm(),
+            sig.hash_algorithm()
+        ))
+    } else if let Err(e) = sig.status() {
+        SignatureVerdict::Error(e.to_string())
+    } else if summary.contains(SignatureSummary::RED) {
+        SignatureVerdict::Bad
+    } else if sig.validity() == gpgme::Validity::Never {
+        SignatureVerdict::Error("GPG key is explicitly distrusted".to_string())
+    } else {
+        // VALID and GREEN also require an owner trust on the key,
+        // which is not supported: a good status is what matters.
+        SignatureVerdict::Good
+    }
+}
+
+// A good signature must also comply with the algorithm policy,
+// for its hash and for the signing (sub)key, and be made by an authorized subkey.
+fn check_signing_policy(
+    sig: &gpgme::Signature,
+    signing_key: Option<&gpgme::Key>,
+    fingerprint: &str,
+    policy: &SignaturePolicy,
+) -> Result<(), SignatureVerdict> {
+    policy
+        .algorithms
+        .check_hash(sig.hash_algorithm())
+        .map_err(SignatureVerdict::WeakAlgorithm)?;
+
+    let key = signing_key.ok_or_else(|| {
+        SignatureVerdict::Error(format!("key {} cannot be inspected", fingerprint))
+    })?;
+
+    let subkey = key
+        .subkeys()
+        .find(|subkey| subkey.fingerprint() == Ok(fingerprint))
+        .or_else(|| key.primary_key())
+        .ok_or_else(|| SignatureVerdict::Error(format!("key {} has no key", fingerprint)))?;
+
+    policy
+        .algorithms
+        .check_subkey(&subkey)
+        .map_err(SignatureVerdict::WeakAlgorithm)?;
+
+    if is_subkey_authorized(&policy.authorized_subkeys, key, fingerprint) {
+        Ok(())
     } else {
-        Err(errors.first().unwrap())
+        Err(SignatureVerdict::UnauthorizedSubkey)
     }
 }
+
+// Evaluate every signature independently: a bad signature
+// must neither hide nor poison another valid one.
+fn check_signature(
+    sig: &gpgme::Signature,
+    gpg_ctx: &mut Context,
+    policy: &SignaturePolicy,
+) -> SignatureCheck {
+    let fingerprint = sig.fingerprint().unwrap_or("unknown").to_string();
+    let signing_key = gpg_ctx.get_key(&fingerprint).ok();
+
+    let verdict = match signature_verdict(sig) {
+        SignatureVerdict::Good => {
+            match check_signing_policy(sig, signing_key.as_ref(), &fingerprint, policy) {
+                Ok(()) => SignatureVerdict::Good,
+                Err(verdict) => verdict,
+            }
+        }
+        verdict => verdict,
+    };
+
+    SignatureCheck {
+        primary_fingerprint: signing_key
+            .as_ref()
+            .and_then(|key| key.fingerprint().ok())
+            .map(String::from),
+        fingerprint,
+        verdict,
+        validity: sig.validity(),
+    }
+}
+
+// Apply the signature rule on individual checks.
+fn apply_signature_rule(checks: &[SignatureCheck], rule: SignatureRule) -> Result<(), String> {
+    if checks.is_empty() {
+        return Err("No signature found".to_string());
+    }
+
+    let valid_count = checks.iter().filter(|check| check.is_valid()).count();
+
+    match rule {
+        SignatureRule::Any if valid_count == 0 => Err("No valid signature".to_string()),
+        SignatureRule::All if valid_count < checks.len() => Err(format!(
+            "{} of {} signatures are invalid, all must be valid",
+            checks.len() - valid_count,
+            checks.len()
+        )),
+        SignatureRule::AtLeast(n) if valid_count < n => Err(format!(
+            "{} valid signature(s) of {}, at least {} required",
+            valid_count,
+            checks.len(),
+            n
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Check each signature of a verification result, without applying the signature rule.
+pub fn check_signatures(
+    verification_result: &VerificationResult,
+    gpg_ctx: &mut Context,
+    policy: &SignaturePolicy,
+) -> Vec<SignatureCheck> {
+    verification_result
+        .signatures()
+        .map(|sig| check_signature(&sig, gpg_ctx, policy))
+        .collect()
+}
+
+// Verify a message has been signed by known keys.
+// Each signature is checked and reported individually,
+// then the signature rule decides whether the message is trusted.
+//
+// See https://github.com/gpg-rs/gpgme/blob/master/examples/verify.rs
+pub fn verify_gpg_signature_result(
+    verification_result: VerificationResult,
+    gpg_ctx: &mut Context,
+    policy: &SignaturePolicy,
+) -> Result<Vec<SignatureCheck>, String> {
+    let checks = check_signatures(&verification_result, gpg_ctx, policy);
+
+    for check in &checks {
+        if check.is_valid() {
+            println!(
+                "   ✅ Signature by {}: {} (key validity: {})",
+                check.signer(),
+                check.verdict,
+                check.validity
+            );
+        } else {
+            println!("   🔴 Signature by {}: {}", check.signer(), check.verdict);
+        }
+    }
+
+    apply_signature_rule(&checks, policy.rule).map(|_| checks)
+}
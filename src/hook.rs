@@ -0,0 +1,306 @@
+// Git hooks: the server-side pre-receive hook, and client-side hooks installed
+// in the repository of developers: pre-push and post-merge.
+//
+// On the server, keys are imported from the repository itself in a throwaway keyring:
+// no keyring nor working tree is needed.
+
+use crate::config::{TAG_NAME, read_or_update_local_config, read_policy_config};
+use crate::git::{check_tag_exists, find_commit, open_repo};
+use crate::gpg::{EphemeralHome, create_gpg_context};
+use crate::verify::{check_range, load_authorized_keys, verify_commit, verify_tag};
+use git2::{Error as GitError, Oid, Repository, RepositoryOpenFlags};
+use std::ffi::OsStr;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+/// A ref update as received on the standard input of a pre-receive hook.
+pub struct RefUpdate {
+    pub old: Oid,
+    pub new: Oid,
+    pub name: String,
+}
+
+impl RefUpdate {
+    // `<old> <new> <ref>`
+    fn parse(line: &str) -> Result<RefUpdate, GitError> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        match fields.as_slice() {
+            [old, new, name] => Ok(RefUpdate {
+                old: Oid::from_str(old)?,
+                new: Oid::from_str(new)?,
+                name: name.to_string(),
+            }),
+            _ => Err(GitError::from_str(&format!(
+                "Invalid ref update `{}`, expected `<old> <new> <ref>`",
+                line
+            ))),
+        }
+    }
+}
+
+/// Verify the ref updates of a push read on `input`, as a pre-receive hook.
+/// Returns false when the push must be rejected.
+pub fn pre_receive_command(repo_path: &str, input: impl BufRead) -> Result<bool, GitError> {
+    let mut updates = Vec::new();
+    for line in input.lines() {
+        let line = line.map_err(|e| GitError::from_str(&e.to_string()))?;
+        if !line.trim().is_empty() {
+            updates.push(RefUpdate::parse(&line)?);
+        }
+    }
+
+    pre_receive(repo_path, &updates)
+}
+
+/// Verify every new commit of each ref update with the authorized keys of the old tip,
+/// or of `git-sign-verifier.hookbase` (default: the verification tag) for a new ref.
+pub fn pre_receive(repo_path: &str, updates: &[RefUpdate]) -> Result<bool, GitError> {
+    // Pushed objects are in a quarantine directory until the hook accepts them:
+    // git tells where with GIT_OBJECT_DIRECTORY and GIT_ALTERNATE_OBJECT_DIRECTORIES.
+    let repo = Repository::open_ext(
+        repo_path,
+        RepositoryOpenFlags::FROM_ENV,
+        std::iter::empty::<&OsStr>(),
+    )?;
+    let config = read_policy_config(&repo)?;
+
+    let mut accepted = true;
+
+    for update in updates {
+        // Deletions bring no commit
+        if update.new.is_zero() {
+            continue;
+        }
+
+        let base = if update.old.is_zero() {
+            let base = repo
+                .config()?
+                .get_string("git-sign-verifier.hookbase")
+                .unwrap_or(format!("refs/tags/{}", TAG_NAME));
+            find_commit(&repo, &base).map_err(|e| {
+                GitError::from_str(&format!(
+                    "No base to read authorized keys for new ref {} ({}): {}",
+                    update.name,
+                    base,
+                    e.message()
+                ))
+            })?
+        } else {
+            repo.find_object(update.old, None)?.peel_to_commit()?
+        };
+
+        let home = EphemeralHome::new("hook").map_err(|e| GitError::from_str(&e))?;
+        let mut gpg_ctx = home.context();
+        let policy = load_authorized_keys(&repo, &mut gpg_ctx, &base, &config)?;
+
+        let mut rejected = Vec::new();
+        for oid in new_commits(&repo, update)? {
+            match verify_commit(&repo, &mut gpg_ctx, oid, &policy) {
+                Ok(true) => {}
+                Ok(false) => rejected.push(format!("commit {} is not trusted", oid)),
+                Err(_) => rejected.push(format!("commit {} is not signed with GPG", oid)),
+            }
+        }
+
+        if rejected.is_empty() {
+            println!("✅ {} accepted", update.name);
+        } else {
+            for reason in &rejected {
+                eprintln!("🔴 {} rejected: {}", update.name, reason);
+            }
+            accepted = false;
+        }
+    }
+
+    Ok(accepted)
+}
+
+/// Verify the refs of a push read on `input`, as a pre-push hook:
+/// `<local ref> <local sha> <remote ref> <remote sha>` lines. Commits between
+/// the verification tag and each pushed commit must be trusted. The tag is not moved.
+/// Returns false when the push must be aborted.
+pub fn pre_push_command(repo_path: &str, input: impl BufRead) -> Result<bool, GitError> {
+    let mut pushed = Vec::new();
+    for line in input.lines() {
+        let line = line.map_err(|e| GitError::from_str(&e.to_string()))?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        match fields.as_slice() {
+            [] => {}
+            [local_ref, local, _, _] => pushed.push((local_ref.to_string(), Oid::from_str(local)?)),
+            _ => {
+                return Err(GitError::from_str(&format!(
+                    "Invalid pushed ref `{}`, expected `<local ref> <local sha> <remote ref> <remote sha>`",
+                    line
+                )));
+            }
+        }
+    }
+
+    // Deletions push no commit
+    pushed.retain(|(_, local)| !local.is_zero());
+    if pushed.is_empty() {
+        return Ok(true);
+    }
+
+    let repo = open_repo(repo_path);
+    let config = read_or_update_local_config(&repo, None)?;
+    let mut gpg_ctx = create_gpg_context(&config);
+
+    let Some(tag_ref) = check_tag_exists(&repo) else {
+        return Err(GitError::from_str(&format!(
+            "Tag {} doesn't exist!",
+            TAG_NAME
+        )));
+    };
+    let tag_oid = tag_ref.target().unwrap();
+    if !verify_tag(&repo, &mut gpg_ctx, tag_oid, &config.signature_policy)? {
+        return Ok(false);
+    }
+
+    let tag_commit = tag_ref.peel_to_commit()?;
+    let policy = load_authorized_keys(&repo, &mut gpg_ctx, &tag_commit, &config)?;
+
+    let mut accepted = true;
+    for (name, local) in &pushed {
+        if check_range(&repo, tag_oid, *local, &mut gpg_ctx, &policy)?.valid {
+            println!("✅ {} can be pushed", name);
+        } else {
+            eprintln!("🔴 {} has untrusted commits, push aborted", name);
+            accepted = false;
+        }
+    }
+
+    Ok(accepted)
+}
+
+// Commits brought by an update: reachable from the new tip, but neither from the old tip
+// nor from any existing ref, as refs are only updated once the hook accepted them.
+fn new_commits(repo: &Repository, update: &RefUpdate) -> Result<Vec<Oid>, GitError> {
+    let mut commits = repo.revwalk()?;
+    commits.push(repo.find_object(update.new, None)?.peel_to_commit()?.id())?;
+
+    if !update.old.is_zero() {
+        commits.hide(repo.find_object(update.old, None)?.peel_to_commit()?.id())?;
+    }
+    for reference in repo.references()? {
+        if let Ok(commit) = reference?.peel_to_commit() {
+            commits.hide(commit.id())?;
+        }
+    }
+
+    commits.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+    commits.collect()
+}
+
+// Marks installed hooks, so that only they are updated or removed
+const HOOK_MARKER: &str = "# Installed by git-sign-verifier";
+
+// Client-side hooks checking commits before they are pushed or after they are merged,
+// without moving the tag: only trusted commits from upstream should move it.
+const CLIENT_HOOKS: [&str; 2] = ["pre-push", "post-merge"];
+
+fn client_hook_script(hook: &str) -> String {
+    // The pushed refs are read on stdin, and may not be HEAD
+    let command = match hook {
+        "pre-push" => "hook pre-push",
+        _ => "verify --no-advance",
+    };
+
+    format!(
+        "#!/bin/sh\n{}, removed by `git-sign-verifier hooks uninstall`\nexec git-sign-verifier {}\n",
+        HOOK_MARKER, command
+    )
+}
+
+// Hooks directory: `core.hooksPath`, relative to the workdir, or `.git/hooks`
+fn hooks_dir(repo: &Repository) -> Result<PathBuf, GitError> {
+    match repo.config()?.get_path("core.hooksPath") {
+        Ok(path) if path.is_absolute() => Ok(path),
+        Ok(path) => Ok(repo.workdir().unwrap_or_else(|| repo.path()).join(path)),
+        Err(_) => Ok(repo.path().join("hooks")),
+    }
+}
+
+fn is_installed_hook(path: &Path) -> bool {
+    std::fs::read_to_string(path)
+        .map(|content| content.lines().any(|line| line.starts_with(HOOK_MARKER)))
+        .unwrap_or(false)
+}
+
+/// Install the pre-push hook, which verifies the pushed commits,
+/// and the post-merge hook, which runs `verify --no-advance`.
+/// Hooks which were not installed by git-sign-verifier are never overwritten.
+pub fn hooks_install_command(repo_path: &str) -> Result<(), GitError> {
+    let repo = open_repo(repo_path);
+    let dir = hooks_dir(&repo)?;
+
+    // Check all hooks first, not to install only some of them
+    for hook in CLIENT_HOOKS {
+        let path = dir.join(hook);
+        if path.exists() && !is_installed_hook(&path) {
+            return Err(GitError::from_str(&format!(
+                "Hook {} already exists and was not installed by git-sign-verifier",
+                path.display()
+            )));
+        }
+    }
+
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| GitError::from_str(&format!("Failed to create {}: {}", dir.display(), e)))?;
+
+    for hook in CLIENT_HOOKS {
+        let path = dir.join(hook);
+        std::fs::write(&path, client_hook_script(hook)).map_err(|e| {
+            GitError::from_str(&format!("Failed to write {}: {}", path.display(), e))
+        })?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).map_err(
+                |e| {
+                    GitError::from_str(&format!(
+                        "Failed to make {} executable: {}",
+                        path.display(),
+                        e
+                    ))
+                },
+            )?;
+        }
+
+        println!("✅ Hook {} installed", path.display());
+    }
+
+    Ok(())
+}
+
+/// Remove the hooks installed by `hooks_install_command`, leaving any other hook.
+pub fn hooks_uninstall_command(repo_path: &str) -> Result<(), GitError> {
+    let repo = open_repo(repo_path);
+    let dir = hooks_dir(&repo)?;
+
+    for hook in CLIENT_HOOKS {
+        let path = dir.join(hook);
+
+        if !path.exists() {
+            continue;
+        }
+
+        if !is_installed_hook(&path) {
+            println!(
+                "⚠️ Hook {} was not installed by git-sign-verifier, kept",
+                path.display()
+            );
+            continue;
+        }
+
+        std::fs::remove_file(&path).map_err(|e| {
+            GitError::from_str(&format!("Failed to remove {}: {}", path.display(), e))
+        })?;
+        println!("🗑️ Hook {} removed", path.display());
+    }
+
+    Ok(())
+}
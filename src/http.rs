@@ -0,0 +1,150 @@
+// Minimal HTTP client, used to fetch keys from a local mirror.
+// Only plain HTTP is supported: keys are validated after download anyway,
+// and TLS would require a whole stack of dependencies.
+
+use git2::Error as GitError;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+// Far above any authorized keys file: a larger response is not a key file
+const MAX_RESPONSE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// GET the body of an `http://` URL. Redirections are not followed, but reported
+/// with their location.
+pub fn get(url: &str) -> Result<Vec<u8>, GitError> {
+    let Some(location) = url.strip_prefix("http://") else {
+        return Err(GitError::from_str(&format!(
+            "Unsupported URL {}: only http:// is supported, serve keys from a local mirror",
+            url
+        )));
+    };
+
+    let (authority, path) = match location.find(['/', '?', '#']) {
+        Some(index) => location.split_at(index),
+        None => (location, "/"),
+    };
+    let path = match path.split_once('#') {
+        Some((path, _fragment)) => path,
+        None => path,
+    };
+    let path = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{}", path)
+    };
+    let (host, port) = parse_authority(authority)
+        .map_err(|e| GitError::from_str(&format!("Invalid URL {}: {}", url, e)))?;
+
+    let http_error = |e: std::io::Error| GitError::from_str(&format!("GET {} failed: {}", url, e));
+
+    let mut stream = TcpStream::connect((host, port)).map_err(http_error)?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(http_error)?;
+    stream
+        .set_write_timeout(Some(TIMEOUT))
+        .map_err(http_error)?;
+
+    // HTTP/1.0 so that the body is neither chunked nor kept alive
+    write!(
+        stream,
+        "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: git-sign-verifier\r\nConnection: close\r\n\r\n",
+        path, authority
+    )
+    .map_err(http_error)?;
+
+    let mut response = Vec::new();
+    stream
+        .take(MAX_RESPONSE_SIZE + 1)
+        .read_to_end(&mut response)
+        .map_err(http_error)?;
+    if response.len() as u64 > MAX_RESPONSE_SIZE {
+        return Err(GitError::from_str(&format!(
+            "GET {} failed: response is larger than {} bytes",
+            url, MAX_RESPONSE_SIZE
+        )));
+    }
+
+    let Some(header_end) = response.windows(4).position(|window| window == b"\r\n\r\n") else {
+        return Err(GitError::from_str(&format!(
+            "GET {} failed: malformed response",
+            url
+        )));
+    };
+
+    let headers = String::from_utf8_lossy(&response[..header_end]);
+    let status = headers
+        .lines()
+        .next()
+        .and_then(|status_line| status_line.split_whitespace().nth(1))
+        .unwrap_or("unknown");
+
+    if status.starts_with('3')
+        && let Some(location) = header(&headers, "Location")
+    {
+        return Err(GitError::from_str(&format!(
+            "GET {} was redirected ({}) to {}: redirections are not followed, use this URL instead",
+            url, status, location
+        )));
+    }
+
+    if status != "200" {
+        return Err(GitError::from_str(&format!(
+            "GET {} failed with status {}",
+            url, status
+        )));
+    }
+
+    Ok(response[header_end + 4..].to_vec())
+}
+
+// Host and port of `host[:port]`, where host may be a bracketed IPv6 address.
+// Credentials are refused: they would be sent in clear text.
+fn parse_authority(authority: &str) -> Result<(&str, u16), String> {
+    if authority.contains('@') {
+        return Err("credentials in URLs are not supported".to_string());
+    }
+
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(bracketed) => {
+            let Some((host, rest)) = bracketed.split_once(']') else {
+                return Err("unclosed bracket in IPv6 address".to_string());
+            };
+            match rest {
+                "" => (host, None),
+                _ => match rest.strip_prefix(':') {
+                    Some(port) => (host, Some(port)),
+                    None => return Err(format!("unexpected `{}` after IPv6 address", rest)),
+                },
+            }
+        }
+        None => match authority.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+
+    if host.is_empty() {
+        return Err("missing host".to_string());
+    }
+
+    let port = match port {
+        None | Some("") => 80,
+        Some(port) => port
+            .parse::<u16>()
+            .map_err(|_| format!("invalid port `{}`", port))?,
+    };
+
+    Ok((host, port))
+}
+
+// Value of a response header, whose name is case insensitive
+fn header<'a>(headers: &'a str, name: &str) -> Option<&'a str> {
+    headers.lines().skip(1).find_map(|line| {
+        let (header_name, value) = line.split_once(':')?;
+        header_name
+            .trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim())
+    })
+}
@@ -1,9 +1,12 @@
-use crate::config::{AUTHORIZED_KEYS_FILE, TAG_NAME, read_or_update_local_config};
+use crate::config::{AUTHORIZED_KEYS_FILE, RESETS_REFS, TAG_NAME, read_or_update_local_config};
 use crate::git::{
     add_tag, check_tag_exists, get_file_content_from_commit, get_last_commit, open_repo,
     print_commit,
 };
-use git2::Error as GitError;
+use crate::gpg::create_gpg_context;
+use crate::verify::verify_tag;
+use git2::{Commit, Error as GitError, Repository};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn init_command(repo_path: &str, gpgme_home_dir: Option<String>) -> Result<(), GitError> {
     let repo = open_repo(repo_path);
@@ -16,23 +19,109 @@ pub fn init_command(repo_path: &str, gpgme_home_dir: Option<String>) -> Result<(
     }
 
     let commit = get_last_commit(&repo)?;
+    check_authorized_keys_file(&repo, &commit)?;
 
-    match get_file_content_from_commit(&repo, &commit, AUTHORIZED_KEYS_FILE)? {
-        Some(_) => true,
-        None => {
-            return Err(GitError::from_str(&format!(
-                "Authorized keys file not found. You must first commit a {} file containing allowed keys.",
-                AUTHORIZED_KEYS_FILE,
-            )));
-        }
+    read_or_update_local_config(&repo, gpgme_home_dir)?;
+
+    add_tag(&repo, &commit, None)?;
+
+    println!("Tag '{}' initialized on commit:", TAG_NAME);
+    print_commit(&commit);
+
+    Ok(())
+}
+
+/// Move an existing tag to the last commit, e.g. after a rotation of the verifier key
+/// or a deliberate rewrite of history. The old tag must be trusted unless `force` is set.
+/// The old tag is kept under `refs/git-sign-verifier/resets/`, and the new tag message
+/// records the reason and both targets.
+pub fn reset_command(
+    repo_path: &str,
+    gpgme_home_dir: Option<String>,
+    reason: &str,
+    force: bool,
+) -> Result<(), GitError> {
+    let repo = open_repo(repo_path);
+
+    if reason.trim().is_empty() {
+        return Err(GitError::from_str("A reason is required to reset the tag"));
+    }
+
+    let Some(old_ref) = check_tag_exists(&repo) else {
+        return Err(GitError::from_str(&format!(
+            "Tag {} doesn't exist: nothing to reset, use init.",
+            TAG_NAME
+        )));
     };
+    let old_tag_oid = old_ref.target().unwrap();
+    let old_commit = old_ref.peel_to_commit()?;
+
+    // The old tag is verified with the keyring in use before the reset
+    let config = read_or_update_local_config(&repo, None)?;
+    let mut gpg_ctx = create_gpg_context(&config);
+    let old_tag_trusted = verify_tag(&repo, &mut gpg_ctx, old_tag_oid, &config.signature_policy)?;
+
+    if !old_tag_trusted && !force {
+        return Err(GitError::from_str(&format!(
+            "Tag {} is not trusted: use --force to reset it anyway",
+            TAG_NAME
+        )));
+    }
+
+    let commit = get_last_commit(&repo)?;
+    check_authorized_keys_file(&repo, &commit)?;
 
     read_or_update_local_config(&repo, gpgme_home_dir)?;
 
-    add_tag(&repo, &commit)?;
+    // Keep the old tag object reachable, as an audit trail
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let history_ref = format!(
+        "{}/{}-{}",
+        RESETS_REFS,
+        timestamp,
+        &old_tag_oid.to_string()[..7]
+    );
+    repo.reference(
+        &history_ref,
+        old_tag_oid,
+        false,
+        &format!("{} reset: {}", TAG_NAME, reason),
+    )?;
 
-    println!("Tag '{}' initialized on commit:", TAG_NAME);
+    let details = format!(
+        "Reset: {}\nPrevious target: {}\nPrevious tag: {} ({})\nNew target: {}",
+        reason.trim(),
+        old_commit.id(),
+        old_tag_oid,
+        if old_tag_trusted {
+            "trusted"
+        } else {
+            "not trusted, forced"
+        },
+        commit.id()
+    );
+    add_tag(&repo, &commit, Some(&details))?;
+
+    println!(
+        "Tag '{}' reset from {} to commit:",
+        TAG_NAME,
+        old_commit.id()
+    );
     print_commit(&commit);
+    println!("\nPrevious tag kept as {}", history_ref);
 
     Ok(())
 }
+
+fn check_authorized_keys_file(repo: &Repository, commit: &Commit) -> Result<(), GitError> {
+    match get_file_content_from_commit(repo, commit, AUTHORIZED_KEYS_FILE)? {
+        Some(_) => Ok(()),
+        None => Err(GitError::from_str(&format!(
+            "Authorized keys file not found. You must first commit a {} file containing allowed keys.",
+            AUTHORIZED_KEYS_FILE,
+        ))),
+    }
+}
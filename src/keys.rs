@@ -0,0 +1,640 @@
+use crate::authorized_keys::{
+    ArmoredBlock, AuthorizedKeys, KeyInfo, PUBLIC_KEY_BLOCK, SubkeyInfo, read_authorized_keys_file,
+    read_key_infos,
+};
+use crate::config::{AUTHORIZED_KEYS_FILE, Config, TAG_NAME, read_or_update_local_config};
+use crate::git::{check_tag_exists, find_commit, get_file_content_from_commit, open_repo};
+use crate::gpg::{AlgorithmPolicy, EphemeralHome, create_gpg_context};
+use crate::manifest::Manifest;
+use git2::{Commit, Error as GitError, Repository};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+// Commit from which keys are read: the given revision,
+// or the verification tag, like `verify` does.
+pub fn resolve_keys_commit<'a>(
+    repo: &'a Repository,
+    at: Option<&str>,
+) -> Result<Commit<'a>, GitError> {
+    match at {
+        Some(rev) => find_commit(repo, rev),
+        None => match check_tag_exists(repo) {
+            Some(gitref) => gitref.peel_to_commit(),
+            None => Err(GitError::from_str(&format!(
+                "Tag {} doesn't exist! Use --at to read keys from a revision.",
+                TAG_NAME
+            ))),
+        },
+    }
+}
+
+/// List keys of the authorized keys file at a revision (default: the verification tag).
+pub fn list_authorized_keys(repo_path: &str, at: Option<&str>) -> Result<Vec<KeyInfo>, GitError> {
+    let repo = open_repo(repo_path);
+    let config = read_or_update_local_config(&repo, None)?;
+    let mut gpg_ctx = create_gpg_context(&config);
+
+    let commit = resolve_keys_commit(&repo, at)?;
+    let content = read_authorized_keys_file(&repo, &commit)?;
+
+    read_key_infos(&mut gpg_ctx, &content)
+}
+
+pub fn keys_list_command(repo_path: &str, at: Option<&str>, json: bool) -> Result<(), GitError> {
+    let keys = list_authorized_keys(repo_path, at)?;
+
+    if json {
+        let output = serde_json::to_string_pretty(&keys)
+            .map_err(|e| GitError::from_str(&format!("JSON serialization failed: {}", e)))?;
+        println!("{}", output);
+        return Ok(());
+    }
+
+    println!(
+        "{} key(s) in {} at {}:",
+        keys.len(),
+        AUTHORIZED_KEYS_FILE,
+        at.unwrap_or(TAG_NAME)
+    );
+
+    for key in &keys {
+        print_key(key);
+    }
+
+    Ok(())
+}
+
+// Pretty print a key and its subkeys
+pub fn print_key(key: &KeyInfo) {
+    println!(
+        "\n🔑 {} [{}] {}",
+        key.fingerprint,
+        key.capabilities,
+        key.status()
+    );
+
+    for uid in &key.user_ids {
+        println!("   uid      {}", uid);
+    }
+
+    println!(
+        "   created  {}, expires {}",
+        key.created.as_deref().unwrap_or("unknown"),
+        key.expires.as_deref().unwrap_or("never")
+    );
+
+    if key.capabilities.contains('S') && !key.authorized {
+        println!("   ⚠️ primary key is not authorized to sign");
+    }
+
+    for subkey in &key.subkeys {
+        println!(
+            "   sub      {} {} [{}] {}, created {}, expires {}{}",
+            subkey.fingerprint,
+            subkey.algorithm,
+            subkey.capabilities,
+            subkey.status(),
+            subkey.created.as_deref().unwrap_or("unknown"),
+            subkey.expires.as_deref().unwrap_or("never"),
+            if subkey.capabilities.contains('S') && !subkey.authorized {
+                " (not authorized)"
+            } else {
+                ""
+            }
+        );
+    }
+}
+
+/// Problem found in an authorized keys file.
+#[derive(Debug)]
+pub struct LintProblem {
+    /// Line of the block holding the problem, none for file-wide problems.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// Validate an authorized keys file before it is committed.
+pub fn lint_authorized_keys(
+    gpg_ctx: &mut gpgme::Context,
+    content: &[u8],
+    policy: &AlgorithmPolicy,
+) -> Vec<LintProblem> {
+    let authorized_keys = AuthorizedKeys::parse(content);
+    let mut problems = Vec::new();
+    // Fingerprint -> line of the block defining it
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    if authorized_keys.blocks.is_empty() {
+        problems.push(LintProblem {
+            line: None,
+            message: "no public key block found".to_string(),
+        });
+    }
+
+    for block in &authorized_keys.blocks {
+        for message in lint_block(gpg_ctx, block, policy, &mut seen) {
+            problems.push(LintProblem {
+                line: Some(block.line),
+                message,
+            });
+        }
+    }
+
+    problems
+}
+
+fn lint_block(
+    gpg_ctx: &mut gpgme::Context,
+    block: &ArmoredBlock,
+    policy: &AlgorithmPolicy,
+    seen: &mut HashMap<String, usize>,
+) -> Vec<String> {
+    let keys = match read_block_keys(gpg_ctx, block) {
+        Ok(keys) => keys,
+        Err(problem) => return vec![problem],
+    };
+
+    let mut problems = Vec::new();
+
+    for key in &keys {
+        let fingerprint = key.fingerprint().unwrap_or("unknown").to_string();
+
+        if let Some(line) = seen.get(&fingerprint) {
+            problems.push(format!(
+                "duplicate key {}, already defined at line {}",
+                fingerprint, line
+            ));
+        } else {
+            seen.insert(fingerprint, block.line);
+        }
+
+        problems.extend(key_problems(key, policy));
+    }
+
+    problems
+}
+
+// Keys of a well-formed public key block
+fn read_block_keys(
+    gpg_ctx: &mut gpgme::Context,
+    block: &ArmoredBlock,
+) -> Result<Vec<gpgme::Key>, String> {
+    if block.kind.contains("PRIVATE KEY") {
+        return Err("secret key material must never be committed".to_string());
+    }
+
+    if block.kind != PUBLIC_KEY_BLOCK {
+        return Err(format!("unexpected armored block '{}'", block.kind));
+    }
+
+    if !block.complete {
+        return Err("truncated armor: END line is missing".to_string());
+    }
+
+    let keys = gpgme::Data::from_bytes(block.text.as_bytes())
+        .and_then(|data| gpg_ctx.read_keys(data))
+        .and_then(|keys| keys.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("invalid key data: {}", e))?;
+
+    if keys.is_empty() {
+        return Err("no public key in block".to_string());
+    }
+
+    Ok(keys)
+}
+
+// Reasons for which signatures of a key would be rejected
+fn key_problems(key: &gpgme::Key, policy: &AlgorithmPolicy) -> Vec<String> {
+    let fingerprint = key.fingerprint().unwrap_or("unknown");
+    let mut problems = Vec::new();
+
+    if key.is_revoked() {
+        problems.push(format!("key {} is revoked", fingerprint));
+    } else if key.is_expired() {
+        problems.push(format!("key {} has expired", fingerprint));
+    }
+
+    problems.extend(policy.check_key(key));
+
+    problems
+}
+
+// Algorithm policy of the enclosing repository, if any, so that keys
+// are rejected as `verify` would reject them.
+fn read_config_or_default(directory: &str) -> Result<Config, GitError> {
+    match Repository::discover(directory) {
+        Ok(repo) => read_or_update_local_config(&repo, None),
+        Err(_) => Ok(Config::default()),
+    }
+}
+
+/// Lint authorized keys files, returns whether all files are valid.
+pub fn keys_lint_command(directory: &str, files: &[String]) -> Result<bool, GitError> {
+    let config = read_config_or_default(directory)?;
+    let mut gpg_ctx = create_gpg_context(&config);
+    let mut all_valid = true;
+
+    for file in files {
+        let content = std::fs::read(file)
+            .map_err(|e| GitError::from_str(&format!("Failed to read {}: {}", file, e)))?;
+
+        let problems =
+            lint_authorized_keys(&mut gpg_ctx, &content, &config.signature_policy.algorithms);
+
+        if problems.is_empty() {
+            println!("✅ {}: no problem found", file);
+            continue;
+        }
+
+        all_valid = false;
+        println!("🔴 {}: {} problem(s) found", file, problems.len());
+        for problem in &problems {
+            match problem.line {
+                Some(line) => println!("   {}:{}: {}", file, line, problem.message),
+                None => println!("   {}: {}", file, problem.message),
+            }
+        }
+    }
+
+    Ok(all_valid)
+}
+
+const BUILD_HEADER: &str = "# GPG Authorized Keys
+# Generated by `git-sign-verifier keys build`: do not edit, update the manifest instead.
+";
+
+/// Build the authorized keys file from a manifest of users and key sources.
+/// Returns whether every source provided valid keys. Invalid keys are left out.
+pub fn keys_build_command(
+    directory: &str,
+    manifest_path: &str,
+    output: &str,
+) -> Result<bool, GitError> {
+    let config = read_config_or_default(directory)?;
+    let policy = &config.signature_policy.algorithms;
+    let manifest = Manifest::read(Path::new(manifest_path))?;
+
+    // Keys are imported in a throwaway keyring to be exported one by one
+    let home = EphemeralHome::new("build").map_err(|e| GitError::from_str(&e))?;
+    let mut gpg_ctx = home.context();
+    gpg_ctx.set_armor(true);
+
+    let mut all_valid = true;
+    // Fingerprint -> owner, to deduplicate keys across users
+    let mut owners: HashMap<String, String> = HashMap::new();
+    // Owner -> fingerprint -> source, sorted for a deterministic output
+    let mut user_keys: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+
+    for user in manifest.users.keys() {
+        println!("🔍 Processing {}", user);
+        let keys = user_keys.entry(user.clone()).or_default();
+
+        for source in manifest.sources(user)? {
+            let files = match manifest.fetch(&source) {
+                Ok(files) => files,
+                Err(e) => {
+                    eprintln!("   🔴 {}", e.message());
+                    all_valid = false;
+                    continue;
+                }
+            };
+
+            for (location, content) in files {
+                let blocks = AuthorizedKeys::parse(&content).blocks;
+                if blocks.is_empty() {
+                    eprintln!("   🔴 {}: no public key found", location);
+                    all_valid = false;
+                }
+
+                for block in &blocks {
+                    let block_keys = match read_block_keys(&mut gpg_ctx, block) {
+                        Ok(block_keys) => block_keys,
+                        Err(problem) => {
+                            eprintln!("   🔴 {}:{}: {}", location, block.line, problem);
+                            all_valid = false;
+                            continue;
+                        }
+                    };
+
+                    import_block(&mut gpg_ctx, block)?;
+
+                    for key in &block_keys {
+                        let fingerprint = key.fingerprint().unwrap_or("unknown").to_string();
+
+                        let problems = key_problems(key, policy);
+                        if !problems.is_empty() {
+                            for problem in problems {
+                                eprintln!("   🔴 {}:{}: {}", location, block.line, problem);
+                            }
+                            all_valid = false;
+                            continue;
+                        }
+
+                        match owners.get(&fingerprint) {
+                            Some(owner) if owner != user => {
+                                eprintln!(
+                                    "   ⚠️ key {} is already listed for {}",
+                                    fingerprint, owner
+                                );
+                            }
+                            Some(_) => (),
+                            None => {
+                                owners.insert(fingerprint.clone(), user.clone());
+                                keys.insert(fingerprint, location.clone());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if keys.is_empty() {
+            eprintln!("   🔴 No valid key for {}", user);
+            all_valid = false;
+        }
+    }
+
+    let mut content = String::from(BUILD_HEADER);
+    for (user, keys) in &user_keys {
+        for (fingerprint, location) in keys {
+            let mut armored = Vec::new();
+            gpg_ctx
+                .export(
+                    Some(fingerprint.as_str()),
+                    gpgme::ExportMode::empty(),
+                    &mut armored,
+                )
+                .map_err(|e| {
+                    GitError::from_str(&format!("Failed to export key {}: {}", fingerprint, e))
+                })?;
+
+            content.push_str(&format!(
+                "\n# Owner: {}\n# Source: {}\n# Key: {}\n{}",
+                user,
+                location,
+                fingerprint,
+                String::from_utf8_lossy(&armored)
+            ));
+        }
+    }
+
+    std::fs::write(output, content)
+        .map_err(|e| GitError::from_str(&format!("Failed to write {}: {}", output, e)))?;
+
+    println!(
+        "✅ {} key(s) of {} user(s) written to {}",
+        owners.len(),
+        user_keys.len(),
+        output
+    );
+
+    Ok(all_valid)
+}
+
+fn import_block(gpg_ctx: &mut gpgme::Context, block: &ArmoredBlock) -> Result<(), GitError> {
+    gpgme::Data::from_bytes(block.text.as_bytes())
+        .and_then(|data| gpg_ctx.import(data))
+        .map(|_| ())
+        .map_err(|e| GitError::from_str(&format!("Failed to import keys: {}", e)))
+}
+
+/// Change of a key between two versions of the authorized keys file.
+#[derive(Debug)]
+pub enum KeyChange {
+    Added {
+        fingerprint: String,
+        user_ids: Vec<String>,
+    },
+    Removed {
+        fingerprint: String,
+        user_ids: Vec<String>,
+    },
+    Modified {
+        fingerprint: String,
+        user_ids: Vec<String>,
+        details: Vec<String>,
+    },
+}
+
+/// Compare the authorized keys file between two revisions.
+/// A missing file is considered empty, so that its creation shows all keys as added.
+pub fn diff_authorized_keys(
+    repo_path: &str,
+    rev_a: &str,
+    rev_b: &str,
+) -> Result<Vec<KeyChange>, GitError> {
+    let repo = open_repo(repo_path);
+    let config = read_or_update_local_config(&repo, None)?;
+    let mut gpg_ctx = create_gpg_context(&config);
+
+    let mut read_keys_at = |rev: &str| -> Result<Vec<KeyInfo>, GitError> {
+        let commit = find_commit(&repo, rev)?;
+        match get_file_content_from_commit(&repo, &commit, AUTHORIZED_KEYS_FILE)? {
+            Some(content) => read_key_infos(&mut gpg_ctx, &content),
+            None => Ok(Vec::new()),
+        }
+    };
+
+    let old_keys = read_keys_at(rev_a)?;
+    let new_keys = read_keys_at(rev_b)?;
+
+    Ok(diff_key_infos(&old_keys, &new_keys))
+}
+
+fn diff_key_infos(old_keys: &[KeyInfo], new_keys: &[KeyInfo]) -> Vec<KeyChange> {
+    let find = |keys: &'_ [KeyInfo], fingerprint: &str| -> Option<usize> {
+        keys.iter().position(|key| key.fingerprint == fingerprint)
+    };
+    let mut changes = Vec::new();
+
+    for old_key in old_keys {
+        if find(new_keys, &old_key.fingerprint).is_none() {
+            changes.push(KeyChange::Removed {
+                fingerprint: old_key.fingerprint.clone(),
+                user_ids: old_key.user_ids.clone(),
+            });
+        }
+    }
+
+    for new_key in new_keys {
+        match find(old_keys, &new_key.fingerprint) {
+            None => changes.push(KeyChange::Added {
+                fingerprint: new_key.fingerprint.clone(),
+                user_ids: new_key.user_ids.clone(),
+            }),
+            Some(index) => {
+                let details = key_differences(&old_keys[index], new_key);
+                if !details.is_empty() {
+                    changes.push(KeyChange::Modified {
+                        fingerprint: new_key.fingerprint.clone(),
+                        user_ids: new_key.user_ids.clone(),
+                        details,
+                    });
+                }
+            }
+        }
+    }
+
+    changes
+}
+
+// Human readable differences between two versions of the same key
+fn key_differences(old: &KeyInfo, new: &KeyInfo) -> Vec<String> {
+    let mut details = Vec::new();
+
+    for uid in &new.user_ids {
+        if !old.user_ids.contains(uid) {
+            details.push(format!("user id added: {}", uid));
+        }
+    }
+    for uid in &old.user_ids {
+        if !new.user_ids.contains(uid) {
+            details.push(format!("user id removed: {}", uid));
+        }
+    }
+
+    if old.expires != new.expires {
+        details.push(format!(
+            "expiration: {} -> {}",
+            old.expires.as_deref().unwrap_or("never"),
+            new.expires.as_deref().unwrap_or("never")
+        ));
+    }
+    if old.capabilities != new.capabilities {
+        details.push(format!(
+            "capabilities: {} -> {}",
+            old.capabilities, new.capabilities
+        ));
+    }
+    if !old.revoked && new.revoked {
+        details.push("revoked".to_string());
+    }
+    if old.authorized != new.authorized {
+        details.push(authorization_change("primary key", new.authorized));
+    }
+
+    for subkey in &new.subkeys {
+        match old
+            .subkeys
+            .iter()
+            .find(|old_subkey| old_subkey.fingerprint == subkey.fingerprint)
+        {
+            None => details.push(format!(
+                "subkey added: {} {} [{}]",
+                subkey.fingerprint, subkey.algorithm, subkey.capabilities
+            )),
+            Some(old_subkey) => details.extend(subkey_differences(old_subkey, subkey)),
+        }
+    }
+    for old_subkey in &old.subkeys {
+        if !new
+            .subkeys
+            .iter()
+            .any(|subkey| subkey.fingerprint == old_subkey.fingerprint)
+        {
+            details.push(format!("subkey removed: {}", old_subkey.fingerprint));
+        }
+    }
+
+    details
+}
+
+fn subkey_differences(old: &SubkeyInfo, new: &SubkeyInfo) -> Vec<String> {
+    let mut details = Vec::new();
+
+    if old.expires != new.expires {
+        details.push(format!(
+            "subkey {} expiration: {} -> {}",
+            new.fingerprint,
+            old.expires.as_deref().unwrap_or("never"),
+            new.expires.as_deref().unwrap_or("never")
+        ));
+    }
+    if !old.revoked && new.revoked {
+        details.push(format!("subkey {} revoked", new.fingerprint));
+    }
+    if old.authorized != new.authorized {
+        details.push(authorization_change(
+            &format!("subkey {}", new.fingerprint),
+            new.authorized,
+        ));
+    }
+
+    details
+}
+
+fn authorization_change(key: &str, authorized: bool) -> String {
+    if authorized {
+        format!("{} is now authorized to sign", key)
+    } else {
+        format!("{} is no longer authorized to sign", key)
+    }
+}
+
+pub fn keys_diff_command(repo_path: &str, rev_a: &str, rev_b: &str) -> Result<(), GitError> {
+    let changes = diff_authorized_keys(repo_path, rev_a, rev_b)?;
+
+    if changes.is_empty() {
+        println!(
+            "No change of authorized keys between {} and {}",
+            rev_a, rev_b
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{} key(s) changed in {} between {} and {}:",
+        changes.len(),
+        AUTHORIZED_KEYS_FILE,
+        rev_a,
+        rev_b
+    );
+
+    for change in &changes {
+        match change {
+            KeyChange::Added {
+                fingerprint,
+                user_ids,
+            } => println!("\n➕ added {} {}", fingerprint, user_ids.join(", ")),
+            KeyChange::Removed {
+                fingerprint,
+                user_ids,
+            } => println!("\n➖ removed {} {}", fingerprint, user_ids.join(", ")),
+            KeyChange::Modified {
+                fingerprint,
+                user_ids,
+                details,
+            } => {
+                println!("\n✏️ modified {} {}", fingerprint, user_ids.join(", "));
+                for detail in details {
+                    println!("   {}", detail);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Print a readable version of an authorized keys file, to be used as
+/// a git diff textconv driver. Unreadable content is printed as is.
+pub fn keys_textconv_command(file: &str) -> Result<(), GitError> {
+    let content = std::fs::read(file)
+        .map_err(|e| GitError::from_str(&format!("Failed to read {}: {}", file, e)))?;
+    let config = read_config_or_default(".")?;
+    let mut gpg_ctx = create_gpg_context(&config);
+
+    match read_key_infos(&mut gpg_ctx, &content) {
+        Ok(keys) => {
+            for key in &keys {
+                print_key(key);
+            }
+        }
+        Err(e) => {
+            eprintln!("⚠️ {}: {}", file, e);
+            print!("{}", String::from_utf8_lossy(&content));
+        }
+    }
+
+    Ok(())
+}
@@ -1,8 +1,23 @@
+pub mod audit;
+pub mod authorized_keys;
+pub mod cache;
 pub mod config;
+pub mod doctor;
+pub mod explain;
+pub mod export;
 pub mod git;
 pub mod gpg;
+pub mod hook;
+pub mod http;
 pub mod init;
+pub mod keys;
+pub mod manifest;
+pub mod notes;
+pub mod pull;
+pub mod status;
+pub mod submodules;
 pub mod verify;
 
-pub use init::init_command;
-pub use verify::verify_command;
+pub use init::{init_command, reset_command};
+pub use status::status_command;
+pub use verify::{verify_command, verify_refs_command, verify_tag_command};
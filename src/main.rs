@@ -1,13 +1,39 @@
+mod audit;
+mod authorized_keys;
+mod cache;
 mod config;
+mod doctor;
+mod explain;
+mod export;
 mod git;
 mod gpg;
+mod hook;
+mod http;
 mod init;
+mod keys;
+mod manifest;
+mod notes;
+mod pull;
+mod status;
+mod submodules;
 mod verify;
 
+use audit::audit_command;
+use cache::cache_clear_command;
 use clap::{Parser, Subcommand};
 use config::EXIT_INVALID_SIGNATURE;
-use init::init_command;
-use verify::verify_command;
+use doctor::doctor_command;
+use explain::explain_command;
+use export::export_command;
+use hook::{hooks_install_command, hooks_uninstall_command, pre_push_command, pre_receive_command};
+use init::{init_command, reset_command};
+use keys::{
+    keys_build_command, keys_diff_command, keys_lint_command, keys_list_command,
+    keys_textconv_command,
+};
+use pull::pull_command;
+use status::status_command;
+use verify::{verify_command, verify_refs_command, verify_tag_command};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -24,9 +50,21 @@ enum Commands {
         #[arg(short, long, default_value = ".")]
         directory: String,
 
-        /// GnuPG home dir (relative path to workdir), in which trusted public keys are stored (in pubring.kbx file).
+        /// GnuPG home dir (relative path to workdir, or to the git dir of a bare repository), in which trusted public keys are stored (in pubring.kbx file).
         #[arg(short, long, required = false)]
         gpgme_home_dir: Option<String>,
+
+        /// Move an existing tag to the last commit. The old tag must be trusted.
+        #[arg(long, requires = "reason")]
+        reset: bool,
+
+        /// Reason of the reset, recorded in the tag message
+        #[arg(long, requires = "reset")]
+        reason: Option<String>,
+
+        /// Reset the tag even if the old tag is not trusted
+        #[arg(long, requires = "reset")]
+        force: bool,
     },
 
     /// Verify the commits since last tags are signed with authenticated signing keys.
@@ -34,6 +72,240 @@ enum Commands {
         /// Path of repository
         #[arg(short, long, default_value = ".")]
         directory: String,
+
+        /// Verify branches matching this ref or glob (e.g. `main`, `refs/heads/release/*`)
+        /// instead of HEAD, and move a tag per branch. Can be repeated.
+        #[arg(long = "refs")]
+        refs: Vec<String>,
+
+        /// Do not move the tag once commits are verified, e.g. in client hooks.
+        #[arg(long, conflicts_with = "refs")]
+        no_advance: bool,
+
+        /// Fail when the index or working tree differs from HEAD, listing changed and untracked files.
+        #[arg(long, conflicts_with = "refs")]
+        require_clean: bool,
+
+        /// Verify the commits of submodules whose gitlink changed, as with `git-sign-verifier.recursesubmodules`.
+        #[arg(long, conflicts_with = "refs")]
+        recurse_submodules: bool,
+    },
+
+    /// Verify a signed release tag and the commits between the verification tag and the tagged commit.
+    VerifyTag {
+        /// Name of the tag, e.g. `v1.4.0`
+        name: String,
+
+        /// Path of repository
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+    },
+
+    /// Fetch a branch into quarantine, verify it, then fast-forward the branch and working tree.
+    Pull {
+        /// Path of repository
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+
+        /// Remote to fetch from
+        #[arg(long, default_value = "origin")]
+        remote: String,
+
+        /// Branch to fetch and fast-forward
+        #[arg(long, default_value = "main")]
+        branch: String,
+    },
+
+    /// Verify commits up to a commit, then write its tree to a directory or a `.tar` file.
+    Export {
+        /// Path of repository
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+
+        /// Commit to export
+        #[arg(long, default_value = "HEAD")]
+        rev: String,
+
+        /// New or empty directory, or tarball when ending with `.tar`
+        #[arg(long)]
+        to: String,
+    },
+
+    /// Show the verification tag and the commits not verified yet, without modifying anything.
+    Status {
+        /// Path of repository
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+    },
+
+    /// Verify every commit of a range of history with the authorized keys in force at that time.
+    Audit {
+        /// Path of repository
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+
+        /// Start of the range, excluded (default: the whole history)
+        #[arg(long)]
+        from: Option<String>,
+
+        /// End of the range, included
+        #[arg(long, default_value = "HEAD")]
+        to: String,
+
+        /// Write the report as JSON to this file
+        #[arg(long)]
+        report: Option<String>,
+    },
+
+    /// Explain in details the verification of a single commit, to debug a signing setup.
+    Explain {
+        /// Path of repository
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+
+        /// Commit to explain
+        rev: String,
+
+        /// Revision to read the authorized keys file from (default: the verification tag)
+        #[arg(long)]
+        keys_at: Option<String>,
+    },
+
+    /// Check the environment required by `init` and `verify`, and suggest fixes.
+    Doctor {
+        /// Path of repository
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+    },
+
+    /// Inspect the authorized keys.
+    Keys {
+        #[command(subcommand)]
+        command: KeysCommands,
+    },
+
+    /// Run as a git server hook.
+    Hook {
+        #[command(subcommand)]
+        command: HookCommands,
+    },
+
+    /// Manage client-side hooks verifying commits before a push and after a merge.
+    Hooks {
+        #[command(subcommand)]
+        command: HooksCommands,
+    },
+
+    /// Manage the cache of verified commits.
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Invalidate all cached verifications: every commit will be verified again.
+    Clear {
+        /// Path of repository
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum HooksCommands {
+    /// Install a pre-push hook verifying pushed commits, and a post-merge hook running `verify --no-advance`.
+    Install {
+        /// Path of repository
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+    },
+
+    /// Remove the hooks installed by `hooks install`, and only them.
+    Uninstall {
+        /// Path of repository
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum HookCommands {
+    /// Verify pushed commits, reading `<old> <new> <ref>` lines on stdin. Works in bare repositories.
+    PreReceive {
+        /// Path of repository
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+    },
+
+    /// Verify commits about to be pushed, reading `<local ref> <local sha> <remote ref> <remote sha>` lines on stdin.
+    PrePush {
+        /// Path of repository
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum KeysCommands {
+    /// List the keys of the authorized keys file, as trusted by `verify`.
+    List {
+        /// Path of repository
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+
+        /// Revision to read the authorized keys file from (default: the verification tag)
+        #[arg(long)]
+        at: Option<String>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Validate authorized keys files, e.g. in a pre-commit hook.
+    Lint {
+        /// Path of repository, from which the algorithm policy is read
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+
+        /// Files to validate
+        #[arg(default_value = ".gpg_authorized_keys")]
+        files: Vec<String>,
+    },
+
+    /// Generate the authorized keys file from a manifest of users and key sources.
+    Build {
+        /// Path of repository, from which the algorithm policy is read
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+
+        /// Manifest of users and key sources (TOML)
+        manifest: String,
+
+        /// File to write
+        #[arg(short, long, default_value = ".gpg_authorized_keys")]
+        output: String,
+    },
+
+    /// Report keys added, removed or modified between two revisions.
+    Diff {
+        /// Path of repository
+        #[arg(short, long, default_value = ".")]
+        directory: String,
+
+        /// Old revision
+        rev_a: String,
+
+        /// New revision
+        rev_b: String,
+    },
+
+    /// Print a readable version of an authorized keys file, as a git diff textconv driver.
+    Textconv {
+        /// File to convert
+        file: String,
     },
 }
 
@@ -44,6 +316,26 @@ fn main() {
         Commands::Init {
             directory,
             gpgme_home_dir,
+            reset: true,
+            reason,
+            force,
+        } => match reset_command(
+            &directory,
+            gpgme_home_dir,
+            reason.as_deref().unwrap_or(""),
+            force,
+        ) {
+            Ok(_) => (),
+            Err(e) => {
+                eprintln!("Error while resetting: {}", e);
+                std::process::exit(1);
+            }
+        },
+
+        Commands::Init {
+            directory,
+            gpgme_home_dir,
+            ..
         } => match init_command(&directory, gpgme_home_dir) {
             Ok(_) => (),
             Err(e) => {
@@ -52,7 +344,17 @@ fn main() {
             }
         },
 
-        Commands::Verify { directory } => match verify_command(&directory) {
+        Commands::Verify {
+            directory,
+            refs,
+            no_advance,
+            require_clean,
+            recurse_submodules,
+        } => match if refs.is_empty() {
+            verify_command(&directory, !no_advance, require_clean, recurse_submodules)
+        } else {
+            verify_refs_command(&directory, &refs)
+        } {
             Ok(valid) => {
                 if !valid {
                     std::process::exit(EXIT_INVALID_SIGNATURE);
@@ -63,5 +365,213 @@ fn main() {
                 std::process::exit(1);
             }
         },
+
+        Commands::VerifyTag { name, directory } => match verify_tag_command(&directory, &name) {
+            Ok(valid) => {
+                if !valid {
+                    std::process::exit(EXIT_INVALID_SIGNATURE);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error while verifying tag {}: {}", name, e);
+                std::process::exit(1);
+            }
+        },
+
+        Commands::Pull {
+            directory,
+            remote,
+            branch,
+        } => match pull_command(&directory, &remote, &branch) {
+            Ok(valid) => {
+                if !valid {
+                    std::process::exit(EXIT_INVALID_SIGNATURE);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error while pulling {}/{}: {}", remote, branch, e);
+                std::process::exit(1);
+            }
+        },
+
+        Commands::Export { directory, rev, to } => match export_command(&directory, &rev, &to) {
+            Ok(valid) => {
+                if !valid {
+                    std::process::exit(EXIT_INVALID_SIGNATURE);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error while exporting {}: {}", rev, e);
+                std::process::exit(1);
+            }
+        },
+
+        Commands::Status { directory } => match status_command(&directory) {
+            Ok(trusted) => {
+                if !trusted {
+                    std::process::exit(EXIT_INVALID_SIGNATURE);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error while reading status: {}", e);
+                std::process::exit(1);
+            }
+        },
+
+        Commands::Audit {
+            directory,
+            from,
+            to,
+            report,
+        } => match audit_command(&directory, from.as_deref(), &to, report.as_deref()) {
+            Ok(trusted) => {
+                if !trusted {
+                    std::process::exit(EXIT_INVALID_SIGNATURE);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error while auditing: {}", e);
+                std::process::exit(1);
+            }
+        },
+
+        Commands::Explain {
+            directory,
+            rev,
+            keys_at,
+        } => match explain_command(&directory, &rev, keys_at.as_deref()) {
+            Ok(trusted) => {
+                if !trusted {
+                    std::process::exit(EXIT_INVALID_SIGNATURE);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error while explaining commit: {}", e);
+                std::process::exit(1);
+            }
+        },
+
+        Commands::Doctor { directory } => match doctor_command(&directory) {
+            Ok(healthy) => {
+                if !healthy {
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error while diagnosing: {}", e);
+                std::process::exit(1);
+            }
+        },
+
+        Commands::Hook { command } => match command {
+            HookCommands::PreReceive { directory } => {
+                match pre_receive_command(&directory, std::io::stdin().lock()) {
+                    Ok(accepted) => {
+                        if !accepted {
+                            std::process::exit(EXIT_INVALID_SIGNATURE);
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Error while verifying pushed commits: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            HookCommands::PrePush { directory } => {
+                match pre_push_command(&directory, std::io::stdin().lock()) {
+                    Ok(accepted) => {
+                        if !accepted {
+                            std::process::exit(EXIT_INVALID_SIGNATURE);
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Error while verifying commits to push: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        },
+
+        Commands::Hooks { command } => {
+            let result = match command {
+                HooksCommands::Install { directory } => hooks_install_command(&directory),
+                HooksCommands::Uninstall { directory } => hooks_uninstall_command(&directory),
+            };
+
+            if let Err(e) = result {
+                eprintln!("Error while managing hooks: {}", e);
+                std::process::exit(1);
+            }
+        }
+
+        Commands::Cache { command } => match command {
+            CacheCommands::Clear { directory } => {
+                if let Err(e) = cache_clear_command(&directory) {
+                    eprintln!("Error while clearing the cache: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        },
+
+        Commands::Keys { command } => match command {
+            KeysCommands::List {
+                directory,
+                at,
+                json,
+            } => {
+                if let Err(e) = keys_list_command(&directory, at.as_deref(), json) {
+                    eprintln!("Error while listing keys: {}", e);
+                    std::process::exit(1);
+                }
+            }
+
+            KeysCommands::Lint { directory, files } => {
+                match keys_lint_command(&directory, &files) {
+                    Ok(valid) => {
+                        if !valid {
+                            std::process::exit(1);
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Error while linting keys: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+
+            KeysCommands::Build {
+                directory,
+                manifest,
+                output,
+            } => match keys_build_command(&directory, &manifest, &output) {
+                Ok(valid) => {
+                    if !valid {
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error while building keys: {}", e);
+                    std::process::exit(1);
+                }
+            },
+
+            KeysCommands::Diff {
+                directory,
+                rev_a,
+                rev_b,
+            } => {
+                if let Err(e) = keys_diff_command(&directory, &rev_a, &rev_b) {
+                    eprintln!("Error while comparing keys: {}", e);
+                    std::process::exit(1);
+                }
+            }
+
+            KeysCommands::Textconv { file } => {
+                if let Err(e) = keys_textconv_command(&file) {
+                    eprintln!("Error while converting keys: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        },
     }
 }
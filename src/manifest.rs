@@ -0,0 +1,109 @@
+// Manifest of the identities allowed to sign, from which `keys build`
+// generates the authorized keys file:
+//
+// url_template = "http://keys.mirror.local/{user}.gpg"
+//
+// [users]
+// alice = []                   # fetched from url_template
+// bob = ["keys/bob.asc"]       # exported key file
+// carol = ["keys/carol"]       # directory of exported key files
+// web-flow = ["http://keys.mirror.local/web-flow.gpg"]
+//
+// `{user}` is replaced by the user name in URLs.
+// Relative paths are resolved from the directory of the manifest.
+
+use crate::http;
+use git2::Error as GitError;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const USER_PLACEHOLDER: &str = "{user}";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// Source of users without any source.
+    pub url_template: Option<String>,
+    /// Key sources by user, sorted by user name.
+    #[serde(default)]
+    pub users: BTreeMap<String, Vec<String>>,
+    #[serde(skip)]
+    base_dir: PathBuf,
+}
+
+impl Manifest {
+    pub fn read(path: &Path) -> Result<Manifest, GitError> {
+        let content = fs::read_to_string(path).map_err(|e| {
+            GitError::from_str(&format!("Failed to read {}: {}", path.display(), e))
+        })?;
+
+        let mut manifest: Manifest = toml::from_str(&content).map_err(|e| {
+            GitError::from_str(&format!("Invalid manifest {}: {}", path.display(), e))
+        })?;
+        manifest.base_dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+
+        Ok(manifest)
+    }
+
+    /// Sources of a user, with `{user}` replaced.
+    pub fn sources(&self, user: &str) -> Result<Vec<String>, GitError> {
+        let sources = match self.users.get(user) {
+            Some(sources) if !sources.is_empty() => sources.clone(),
+            _ => match &self.url_template {
+                Some(template) => vec![template.clone()],
+                None => {
+                    return Err(GitError::from_str(&format!(
+                        "No source for user {} and no url_template in manifest",
+                        user
+                    )));
+                }
+            },
+        };
+
+        Ok(sources
+            .iter()
+            .map(|source| source.replace(USER_PLACEHOLDER, user))
+            .collect())
+    }
+
+    /// Fetch the content of a source: an HTTP URL, a directory or a file.
+    /// Returns the location and content of each fetched file, in a stable order.
+    pub fn fetch(&self, source: &str) -> Result<Vec<(String, Vec<u8>)>, GitError> {
+        if source.contains("://") {
+            return Ok(vec![(source.to_string(), http::get(source)?)]);
+        }
+
+        let path = self.base_dir.join(source);
+        let read_error = |e: std::io::Error| {
+            GitError::from_str(&format!("Failed to read {}: {}", path.display(), e))
+        };
+
+        if !path.is_dir() {
+            return Ok(vec![(
+                source.to_string(),
+                fs::read(&path).map_err(read_error)?,
+            )]);
+        }
+
+        let mut files = fs::read_dir(&path)
+            .map_err(read_error)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(read_error)?;
+        files.retain(|file| file.is_file());
+        files.sort();
+
+        files
+            .iter()
+            .map(|file| {
+                let location = Path::new(source)
+                    .join(file.file_name().unwrap_or_default())
+                    .to_string_lossy()
+                    .to_string();
+                Ok((location, fs::read(file).map_err(read_error)?))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,111 @@
+// Verification results recorded as git notes, enabled with `git-sign-verifier.notes`.
+//
+// Each commit trusted by `verify`, `verify --refs` or `pull` gets a note in `refs/notes/signatures`
+// with its signers, the time of verification and the verifier identity. Notes of a run are added
+// in a single commit of the notes ref, signed with the verifier key like the verification tag.
+
+use crate::authorized_keys::format_datetime;
+use crate::config::NOTES_REF;
+use crate::git::{add_signed_commit, verifier_signature};
+use crate::verify::VerifiedCommit;
+use git2::{Commit, Error as GitError, Oid, Repository, Signature, Tree};
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+const MODE_BLOB: i32 = 0o100644;
+const MODE_TREE: i32 = 0o040000;
+
+/// Add a note to each verified commit, in a signed commit of the notes ref.
+pub fn record_notes(repo: &Repository, verified: &[VerifiedCommit]) -> Result<(), GitError> {
+    if verified.is_empty() {
+        return Ok(());
+    }
+
+    let verifier = verifier_signature(repo)?;
+    let verified_at = format_datetime(SystemTime::now());
+    let parent = repo
+        .find_reference(NOTES_REF)
+        .and_then(|reference| reference.peel_to_commit())
+        .ok();
+
+    // The notes tree is built in memory: the notes ref only moves to the signed commit
+    let notes: Vec<(Oid, String)> = verified
+        .iter()
+        .map(|commit| (commit.oid, note_content(commit, &verifier, &verified_at)))
+        .collect();
+    let tree = notes_tree(repo, parent.as_ref(), &notes)?;
+    let parents: Vec<&Commit> = parent.iter().collect();
+    add_signed_commit(
+        repo,
+        NOTES_REF,
+        &tree,
+        &parents,
+        &format!(
+            "Notes added by git-sign-verifier for {} commit(s)",
+            verified.len()
+        ),
+    )?;
+
+    println!("📝 {} note(s) recorded in {}", verified.len(), NOTES_REF);
+
+    Ok(())
+}
+
+fn note_content(commit: &VerifiedCommit, verifier: &Signature, verified_at: &str) -> String {
+    let mut note = String::from("Trusted by git-sign-verifier\n");
+    for signer in &commit.signers {
+        note.push_str(&format!("Signer: {}\n", signer));
+    }
+    note.push_str(&format!("Verified-At: {}\n", verified_at));
+    // Signers were recorded in the cache by a previous run
+    if commit.cached {
+        note.push_str("Verified-From: cache\n");
+    }
+    note.push_str(&format!(
+        "Verifier: {} <{}>\n",
+        verifier.name().unwrap_or(""),
+        verifier.email().unwrap_or("")
+    ));
+    note
+}
+
+// Tree of the previous notes with the new ones, fanned out by the first two hex digits
+// of the annotated commit like git does (`ab/cdef...`). A previous note of the same
+// commit is replaced, including a note at the top level written by older versions.
+fn notes_tree<'a>(
+    repo: &'a Repository,
+    parent: Option<&Commit>,
+    notes: &[(Oid, String)],
+) -> Result<Tree<'a>, GitError> {
+    let parent_tree = parent.map(Commit::tree).transpose()?;
+    let mut root = repo.treebuilder(parent_tree.as_ref())?;
+
+    let mut fanout: BTreeMap<String, Vec<(String, Oid)>> = BTreeMap::new();
+    for (oid, note) in notes {
+        let hex = oid.to_string();
+        if root.get(&hex)?.is_some() {
+            root.remove(&hex)?;
+        }
+
+        let blob = repo.blob(note.as_bytes())?;
+        let (dir, name) = hex.split_at(2);
+        fanout
+            .entry(dir.to_string())
+            .or_default()
+            .push((name.to_string(), blob));
+    }
+
+    for (dir, entries) in fanout {
+        let existing = match root.get(&dir)? {
+            Some(entry) if entry.filemode() == MODE_TREE => Some(repo.find_tree(entry.id())?),
+            _ => None,
+        };
+        let mut subtree = repo.treebuilder(existing.as_ref())?;
+        for (name, blob) in entries {
+            subtree.insert(&name, blob, MODE_BLOB)?;
+        }
+        root.insert(&dir, subtree.write()?, MODE_TREE)?;
+    }
+
+    repo.find_tree(root.write()?)
+}
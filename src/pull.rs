@@ -0,0 +1,112 @@
+// Deployment from a remote without ever checking out unverified commits.
+//
+// The branch is fetched into a quarantine ref, commits from the verification tag
+// to the fetched tip are verified, and only then the local branch and working tree
+// are fast-forwarded. Untrusted commits stay in quarantine for inspection.
+
+use crate::config::{QUARANTINE_REFS, TAG_NAME, read_or_update_local_config};
+use crate::git::{add_tag, check_tag_exists, open_repo};
+use crate::gpg::create_gpg_context;
+use crate::notes::record_notes;
+use crate::verify::{check_from_ref, load_authorized_keys, verify_tag};
+use git2::{AutotagOption, Error as GitError, FetchOptions, Repository};
+
+/// Fetch `branch` from `remote`, verify it, then fast-forward the local branch,
+/// its working tree when checked out, and the verification tag.
+/// Returns false when fetched commits are not trusted: nothing is updated then.
+pub fn pull_command(repo_path: &str, remote: &str, branch: &str) -> Result<bool, GitError> {
+    let repo = open_repo(repo_path);
+    let config = read_or_update_local_config(&repo, None)?;
+    let mut gpg_ctx = create_gpg_context(&config);
+
+    let Some(from_ref) = check_tag_exists(&repo) else {
+        return Err(GitError::from_str(&format!(
+            "Tag {} doesn't exist!",
+            TAG_NAME
+        )));
+    };
+    if !verify_tag(
+        &repo,
+        &mut gpg_ctx,
+        from_ref.target().unwrap(),
+        &config.signature_policy,
+    )? {
+        return Ok(false);
+    }
+
+    let quarantine_ref_name = format!("{}/{}/{}", QUARANTINE_REFS, remote, branch);
+    fetch_into(&repo, remote, branch, &quarantine_ref_name)?;
+    let quarantine_ref = repo.find_reference(&quarantine_ref_name)?;
+    let fetched = quarantine_ref.peel_to_commit()?;
+    println!("📥 Fetched {}/{} at {}", remote, branch, fetched.id());
+
+    let tag_commit = from_ref.peel_to_commit()?;
+    let policy = load_authorized_keys(&repo, &mut gpg_ctx, &tag_commit, &config)?;
+
+    let range = check_from_ref(&repo, &from_ref, &quarantine_ref, &mut gpg_ctx, &policy)?;
+    if !range.valid {
+        eprintln!(
+            "🔴 Fetched commits are not trusted: {} left untouched, fetched commits kept in {}",
+            branch, quarantine_ref_name
+        );
+        return Ok(false);
+    }
+
+    let branch_ref_name = format!("refs/heads/{}", branch);
+    if let Ok(local) = repo.refname_to_id(&branch_ref_name)
+        && local != fetched.id()
+        && !repo.graph_descendant_of(fetched.id(), local)?
+    {
+        return Err(GitError::from_str(&format!(
+            "{}/{} is not a fast-forward of {}",
+            remote, branch, branch
+        )));
+    }
+
+    // The working tree is updated before the branch, so that a failed checkout
+    // (e.g. because of local changes) leaves both untouched.
+    let checked_out = !repo.is_bare()
+        && repo
+            .head()
+            .map(|head| head.name() == Some(branch_ref_name.as_str()))
+            .unwrap_or(false);
+    if checked_out {
+        repo.checkout_tree(
+            fetched.as_object(),
+            Some(git2::build::CheckoutBuilder::new().safe()),
+        )?;
+    }
+
+    repo.reference(
+        &branch_ref_name,
+        fetched.id(),
+        true,
+        &format!("git-sign-verifier pull {} {}", remote, branch),
+    )?;
+    println!("⏩ {} fast-forwarded to {}", branch, fetched.id());
+
+    if config.record_notes {
+        record_notes(&repo, &range.verified)?;
+    }
+    add_tag(&repo, &fetched, None)?;
+    println!("Tag {} moved to {}", TAG_NAME, fetched.id());
+
+    Ok(true)
+}
+
+// Fetch a single branch into the given ref, without any tag:
+// a fetched tag could not be trusted before verification.
+fn fetch_into(
+    repo: &Repository,
+    remote: &str,
+    branch: &str,
+    ref_name: &str,
+) -> Result<(), GitError> {
+    let mut remote = repo.find_remote(remote)?;
+    let refspec = format!("+refs/heads/{}:{}", branch, ref_name);
+
+    let mut options = FetchOptions::new();
+    options.download_tags(AutotagOption::None);
+
+    remote.fetch(&[&refspec], Some(&mut options), None)
+}
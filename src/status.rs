@@ -0,0 +1,124 @@
+use crate::authorized_keys::{format_date, read_key_infos};
+use crate::config::{AUTHORIZED_KEYS_FILE, TAG_NAME, read_or_update_local_config};
+use crate::git::{check_tag_exists, get_file_content_from_commit, open_repo, print_commit};
+use crate::gpg::create_gpg_context;
+use crate::verify::check_tag;
+use git2::{Error as GitError, Oid, Repository};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Summarize the verification state without modifying anything:
+/// neither the tag, nor the keyring. Returns whether the tag is trusted.
+pub fn status_command(repo_path: &str) -> Result<bool, GitError> {
+    let repo = open_repo(repo_path);
+    let config = read_or_update_local_config(&repo, None)?;
+    let mut gpg_ctx = create_gpg_context(&config);
+
+    match &config.gpgme_home_dir {
+        Some(dir) => println!("🔑 GPG home: {}", dir),
+        None => println!("🔑 GPG home: default keyring of the user"),
+    }
+
+    let Some(tag_ref) = check_tag_exists(&repo) else {
+        return Err(GitError::from_str(&format!(
+            "Tag {} doesn't exist! Run `init` first.",
+            TAG_NAME
+        )));
+    };
+
+    let tag_oid = tag_ref.target().unwrap();
+    let tag_commit = tag_ref.peel_to_commit()?;
+
+    println!("\n📌 Tag {} on commit:", TAG_NAME);
+    print_commit(&tag_commit);
+
+    // The tagger is only a claim of the tag header: the signing key tells who signed it
+    let tag_checks = check_tag(&repo, &mut gpg_ctx, tag_oid, &config.signature_policy)?;
+    if let Ok(tag) = repo.find_tag(tag_oid)
+        && let Some(tagger) = tag.tagger()
+    {
+        let date = UNIX_EPOCH + Duration::from_secs(tagger.when().seconds().max(0) as u64);
+        println!("\n  tagged on {}", format_date(date));
+    }
+    for check in tag_checks.iter().flatten().filter(|check| check.is_valid()) {
+        let user_id = gpg_ctx
+            .get_key(&check.fingerprint)
+            .ok()
+            .and_then(|key| {
+                key.user_ids()
+                    .next()
+                    .and_then(|uid| uid.id().ok().map(String::from))
+            })
+            .unwrap_or_else(|| "unknown user ID".to_string());
+        println!("  signed by {} ({})", check.signer(), user_id);
+    }
+    let tag_trusted = tag_checks.is_some();
+
+    let tag_keys = get_file_content_from_commit(&repo, &tag_commit, AUTHORIZED_KEYS_FILE)?;
+    match &tag_keys {
+        Some(content) => match read_key_infos(&mut gpg_ctx, content) {
+            Ok(keys) => {
+                let unusable = keys.iter().filter(|key| key.revoked || key.expired).count();
+                println!(
+                    "\n🔑 {} at tag: {} key(s), {} revoked or expired",
+                    AUTHORIZED_KEYS_FILE,
+                    keys.len(),
+                    unusable
+                );
+            }
+            Err(e) => println!("\n🔴 {} at tag is unreadable: {}", AUTHORIZED_KEYS_FILE, e),
+        },
+        None => println!("\n🔴 {} is missing at tag", AUTHORIZED_KEYS_FILE),
+    }
+
+    let head = repo.head()?;
+    let head_commit = head.peel_to_commit()?;
+    let head_keys = get_file_content_from_commit(&repo, &head_commit, AUTHORIZED_KEYS_FILE)?;
+    if head_keys != tag_keys {
+        println!(
+            "   ⚠️ {} has changed on HEAD: changes will be trusted after the next verification",
+            AUTHORIZED_KEYS_FILE
+        );
+    }
+
+    let unverified = unverified_commits(&repo, tag_oid, head_commit.id())?;
+    let head_name = head.shorthand().unwrap_or("HEAD");
+
+    if unverified.is_empty() {
+        println!("\n✅ {} is verified", head_name);
+    } else {
+        println!(
+            "\n⏳ {} is {} commit(s) ahead of {}, not verified yet:",
+            head_name,
+            unverified.len(),
+            TAG_NAME
+        );
+        for oid in &unverified {
+            let commit = repo.find_commit(*oid)?;
+            println!("   {} {}", oid, commit.summary().unwrap_or(""));
+        }
+    }
+
+    if !repo.graph_descendant_of(head_commit.id(), tag_commit.id())?
+        && head_commit.id() != tag_commit.id()
+    {
+        println!(
+            "   ⚠️ {} is not an ancestor of {}: history may have been rewritten",
+            TAG_NAME, head_name
+        );
+    }
+
+    Ok(tag_trusted)
+}
+
+// Commits reachable from HEAD but not from the tag, oldest first
+fn unverified_commits(
+    repo: &Repository,
+    tag_oid: Oid,
+    head_oid: Oid,
+) -> Result<Vec<Oid>, GitError> {
+    let mut commits = repo.revwalk()?;
+    commits.push_range(&format!("{}..{}", tag_oid, head_oid))?;
+    commits.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+
+    commits.collect()
+}
@@ -0,0 +1,186 @@
+// Verification of submodules, enabled with `git-sign-verifier.recursesubmodules`
+// or `verify --recurse-submodules`.
+//
+// A commit updating a gitlink only changes a commit id: the commits between the old
+// and the new gitlink are verified in the submodule, with its own authorized keys file
+// read on the old gitlink, or with the keys of the parent repository when it has none.
+
+use crate::config::{AUTHORIZED_KEYS_FILE, Config};
+use crate::gpg::{EphemeralHome, SignaturePolicy};
+use crate::verify::{load_authorized_keys, verify_commit};
+use git2::{Commit, Error as GitError, Oid, Repository, TreeWalkMode, TreeWalkResult};
+use gpgme::Context;
+use std::path::{Path, PathBuf};
+
+const MODE_GITLINK: i32 = 0o160000;
+
+/// Verify the commits brought by gitlink updates from `from` to `to`, recursively.
+/// Gitlinks are read from the trees of both commits, not from `.gitmodules`.
+/// Without `from`, every submodule is new: its whole history is verified.
+/// A changed gitlink whose submodule is not initialized fails verification.
+pub fn verify_submodules(
+    repo: &Repository,
+    from: Option<&Commit>,
+    to: &Commit,
+    gpg_ctx: &mut Context,
+    policy: &SignaturePolicy,
+    config: &Config,
+) -> Result<bool, GitError> {
+    let mut all_valid = true;
+
+    for (path, new) in gitlinks(to)? {
+        let name = path.display().to_string();
+
+        let old = match from {
+            Some(from) => gitlink(from, &path)?,
+            None => None,
+        };
+        if old == Some(new) {
+            continue;
+        }
+
+        let Some(sub_repo) = open_submodule(repo, &path) else {
+            eprintln!(
+                "🔴 Submodule {} is not initialized: its commit {} can't be verified",
+                name, new
+            );
+            all_valid = false;
+            continue;
+        };
+
+        let (Ok(new_commit), Ok(old_commit)) = (
+            sub_repo.find_commit(new),
+            old.map(|old| sub_repo.find_commit(old)).transpose(),
+        ) else {
+            eprintln!(
+                "🔴 Commits of submodule {} are missing: update it before verification",
+                name
+            );
+            all_valid = false;
+            continue;
+        };
+
+        println!(
+            "Verifying submodule {} from {} to {}",
+            name,
+            old.map(|old| old.to_string())
+                .unwrap_or("the start of its history".to_string()),
+            new
+        );
+
+        let valid = match &old_commit {
+            Some(old_commit) if has_authorized_keys_file(old_commit)? => {
+                // Keys of the submodule must not be trusted by the parent repository
+                let home = EphemeralHome::new("submodule").map_err(|e| GitError::from_str(&e))?;
+                let mut sub_ctx = home.context();
+                let sub_policy = load_authorized_keys(&sub_repo, &mut sub_ctx, old_commit, config)?;
+
+                verify_submodule_range(
+                    &sub_repo,
+                    Some(old_commit),
+                    &new_commit,
+                    &mut sub_ctx,
+                    &sub_policy,
+                    config,
+                )?
+            }
+            _ => {
+                println!(
+                    "   No {} in submodule {}: keys of the parent repository are used",
+                    AUTHORIZED_KEYS_FILE, name
+                );
+                verify_submodule_range(
+                    &sub_repo,
+                    old_commit.as_ref(),
+                    &new_commit,
+                    gpg_ctx,
+                    policy,
+                    config,
+                )?
+            }
+        };
+
+        if valid {
+            println!("✅ Submodule {} is trusted", name);
+        } else {
+            eprintln!("🔴 Submodule {} has untrusted commits", name);
+            all_valid = false;
+        }
+    }
+
+    Ok(all_valid)
+}
+
+// Verify the commits of a submodule, then its own submodules
+fn verify_submodule_range(
+    repo: &Repository,
+    from: Option<&Commit>,
+    to: &Commit,
+    gpg_ctx: &mut Context,
+    policy: &SignaturePolicy,
+    config: &Config,
+) -> Result<bool, GitError> {
+    let mut commits = repo.revwalk()?;
+    commits.push(to.id())?;
+    if let Some(from) = from {
+        commits.hide(from.id())?;
+    }
+    commits.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+
+    for oid in commits {
+        let oid = oid?;
+
+        match verify_commit(repo, gpg_ctx, oid, policy) {
+            Ok(true) => continue,
+            Ok(false) => return Ok(false),
+            Err(_) => {
+                eprintln!("🔴 Commit {} is not signed with GPG", oid);
+                return Ok(false);
+            }
+        }
+    }
+
+    verify_submodules(repo, from, to, gpg_ctx, policy, config)
+}
+
+// Paths and commit ids of every submodule in a commit tree
+fn gitlinks(commit: &Commit) -> Result<Vec<(PathBuf, Oid)>, GitError> {
+    let mut gitlinks = Vec::new();
+    commit.tree()?.walk(TreeWalkMode::PreOrder, |dir, entry| {
+        if entry.filemode() == MODE_GITLINK {
+            let name = String::from_utf8_lossy(entry.name_bytes()).into_owned();
+            gitlinks.push((Path::new(dir).join(name), entry.id()));
+        }
+        TreeWalkResult::Ok
+    })?;
+    Ok(gitlinks)
+}
+
+/// Repository of an initialized submodule, found by its path in the working tree.
+/// The submodule may be missing from the `.gitmodules` of the working tree,
+/// which is not the one of the verified commit.
+pub fn open_submodule(repo: &Repository, path: &Path) -> Option<Repository> {
+    if let Some(sub_repo) = path
+        .to_str()
+        .and_then(|path| repo.find_submodule(path).ok())
+        .and_then(|submodule| submodule.open().ok())
+    {
+        return Some(sub_repo);
+    }
+    Repository::open(repo.workdir()?.join(path)).ok()
+}
+
+// Commit id of a submodule in a commit tree
+fn gitlink(commit: &Commit, path: &Path) -> Result<Option<Oid>, GitError> {
+    match commit.tree()?.get_path(path) {
+        Ok(entry) if entry.filemode() == MODE_GITLINK => Ok(Some(entry.id())),
+        _ => Ok(None),
+    }
+}
+
+fn has_authorized_keys_file(commit: &Commit) -> Result<bool, GitError> {
+    Ok(commit
+        .tree()?
+        .get_path(Path::new(AUTHORIZED_KEYS_FILE))
+        .is_ok())
+}
@@ -1,13 +1,34 @@
-use crate::config::{AUTHORIZED_KEYS_FILE, TAG_NAME, read_or_update_local_config};
+use crate::authorized_keys::{
+    AuthorizedKeys, ImportReport, import_authorized_keys, read_authorized_keys_file,
+};
+use crate::cache::{VerificationCache, key_set_id};
+use crate::config::{
+    AUTHORIZED_KEYS_FILE, Config, MARKER_PREFIX, TAG_NAME, read_or_update_local_config,
+};
 use crate::git::{
-    add_tag, check_tag_exists, get_file_content_from_commit, open_repo, print_commit,
+    add_named_tag, add_tag, check_tag_exists, marker_tag_name, open_repo, print_commit,
 };
-use crate::gpg::{create_gpg_context, verify_gpg_signature_result};
+use crate::gpg::{
+    SignatureCheck, SignaturePolicy, SignatureRule, create_gpg_context, verify_gpg_signature_result,
+};
+use crate::notes::record_notes;
+use crate::submodules::verify_submodules;
 use git2::{Commit, Error as GitError, ObjectType, Oid, Reference, Repository};
 use gpgme::Context;
+use std::collections::HashMap;
 use std::io::{BufRead, Write};
-
-pub fn verify_command(repo_path: &str) -> Result<bool, GitError> {
+use std::path::Path;
+
+/// Verify commits from the verification tag to HEAD, and move the tag to HEAD
+/// when they are all trusted, unless `advance` is false.
+/// With `require_clean`, verification also fails when the index or working tree differs from HEAD.
+/// With `recurse_submodules`, submodules are verified even when not enabled in the configuration.
+pub fn verify_command(
+    repo_path: &str,
+    advance: bool,
+    require_clean: bool,
+    recurse_submodules: bool,
+) -> Result<bool, GitError> {
     let repo = open_repo(repo_path);
     let config = read_or_update_local_config(&repo, None)?;
 
@@ -16,7 +37,7 @@ pub fn verify_command(repo_path: &str) -> Result<bool, GitError> {
     let from_ref = match check_tag_exists(&repo) {
         Some(gitref) => {
             let oid = gitref.target().unwrap();
-            match verify_tag(&repo, &mut gpg_ctx, oid) {
+            match verify_tag(&repo, &mut gpg_ctx, oid, &config.signature_policy) {
                 Ok(true) => gitref,
                 Ok(false) => return Ok(false),
                 Err(e) => return Err(e),
@@ -33,54 +54,459 @@ pub fn verify_command(repo_path: &str) -> Result<bool, GitError> {
     // Get the commit that the tag points to
     let tag_commit = from_ref.peel_to_commit()?;
 
-    // Read the authorized keys file from that commit
-    let authorized_keys_content =
-        match get_file_content_from_commit(&repo, &tag_commit, AUTHORIZED_KEYS_FILE)? {
-            Some(content) => content,
-            None => {
-                return Err(GitError::from_str(&format!(
-                    "File '{}' not found in commit {}. This commit cannot be verified.",
-                    AUTHORIZED_KEYS_FILE,
-                    tag_commit.id()
-                )));
+    let policy = load_authorized_keys(&repo, &mut gpg_ctx, &tag_commit, &config)?;
+
+    let to_ref = repo.head()?;
+
+    let range = check_from_ref(&repo, &from_ref, &to_ref, &mut gpg_ctx, &policy)?;
+    let mut all_valid = range.valid;
+
+    if all_valid && (recurse_submodules || config.recurse_submodules) {
+        all_valid = verify_submodules(
+            &repo,
+            Some(&tag_commit),
+            &to_ref.peel_to_commit()?,
+            &mut gpg_ctx,
+            &policy,
+            &config,
+        )?;
+    }
+
+    if all_valid && require_clean {
+        all_valid = check_clean_worktree(&repo, &config)?;
+    }
+
+    if all_valid {
+        println!("🎉 All commits were signed and trusted.");
+        if advance {
+            if config.record_notes {
+                record_notes(&repo, &range.verified)?;
             }
-        };
 
-    // Import the authorized keys into the context
-    let _imported = match gpgme::Data::from_bytes(&authorized_keys_content) {
-        Ok(data) => gpg_ctx.import(data).map_err(|e| {
-            return GitError::from_str(&format!(
-                "Failed to import authorized keys into GPG context: {}",
-                e
-            ));
-        }),
-        Err(e) => {
-            return Err(GitError::from_str(&format!(
-                "Failed to create GPG data from authorized keys: {}",
-                e
-            )));
+            let to_commit = to_ref.peel_to_commit()?;
+            add_tag(&repo, &to_commit, None)?;
+            println!("Tag {} moved to {}", TAG_NAME, to_commit.id());
         }
+    }
+
+    Ok(all_valid)
+}
+
+// Files of the index or working tree differing from HEAD would be deployed
+// without being verified. Ignored files are not reported, nor the keyring
+// of the verifier when it lives in the working tree: it changes with each verification.
+fn check_clean_worktree(repo: &Repository, config: &Config) -> Result<bool, GitError> {
+    let Some(workdir) = repo.workdir() else {
+        return Ok(true);
     };
 
-    let to_ref = repo.head()?;
+    let gpg_home = config
+        .gpgme_home_dir
+        .as_ref()
+        .and_then(|home| std::fs::canonicalize(home).ok())
+        .zip(std::fs::canonicalize(workdir).ok())
+        .and_then(|(home, workdir)| home.strip_prefix(workdir).ok().map(Path::to_path_buf))
+        .filter(|home| !home.as_os_str().is_empty());
+
+    let mut options = git2::StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+
+    let mut changes = Vec::new();
+    for entry in repo.statuses(Some(&mut options))?.iter() {
+        let path = entry.path().unwrap_or("");
+        if gpg_home
+            .as_ref()
+            .is_some_and(|home| Path::new(path).starts_with(home))
+        {
+            continue;
+        }
+
+        let status = entry.status();
+        let change = if status.is_wt_new() {
+            "untracked"
+        } else if status.is_index_new() {
+            "added"
+        } else if status.is_index_deleted() || status.is_wt_deleted() {
+            "deleted"
+        } else {
+            "modified"
+        };
+        changes.push((change, path.to_string()));
+    }
 
-    let all_valid = verify_from_ref(&repo, &from_ref, &to_ref, &mut gpg_ctx)?;
+    if changes.is_empty() {
+        println!("✅ Working tree matches HEAD");
+        return Ok(true);
+    }
+
+    eprintln!("🔴 Working tree differs from HEAD:");
+    for (change, path) in &changes {
+        eprintln!("   {}: {}", change, path);
+    }
+
+    Ok(false)
+}
+
+/// Verify all commits reachable from several refs at once, e.g. `main` and `refs/heads/release/*`.
+/// Commits reachable from the verification tag or from a branch marker are hidden,
+/// the others are verified once, then the marker of each fully trusted branch is moved.
+pub fn verify_refs_command(repo_path: &str, patterns: &[String]) -> Result<bool, GitError> {
+    let repo = open_repo(repo_path);
+    let config = read_or_update_local_config(&repo, None)?;
+    let mut gpg_ctx = create_gpg_context(&config);
+
+    let Some(tag_ref) = check_tag_exists(&repo) else {
+        return Err(GitError::from_str(&format!(
+            "Tag {} doesn't exist!",
+            TAG_NAME
+        )));
+    };
+    if !verify_tag(
+        &repo,
+        &mut gpg_ctx,
+        tag_ref.target().unwrap(),
+        &config.signature_policy,
+    )? {
+        return Ok(false);
+    }
+
+    let tag_commit = tag_ref.peel_to_commit()?;
+
+    // A forged marker would hide commits: markers must be trusted too,
+    // and are verified before developer keys are imported
+    let mut hidden = vec![tag_commit.id()];
+    for marker in repo.references_glob(&format!("refs/tags/{}*", MARKER_PREFIX))? {
+        let marker = marker?;
+        if !verify_marker(&repo, &mut gpg_ctx, &marker, &config.signature_policy)? {
+            eprintln!(
+                "🔴 Marker {} is not trusted",
+                marker.shorthand().unwrap_or("")
+            );
+            return Ok(false);
+        }
+        hidden.push(marker.peel_to_commit()?.id());
+    }
+
+    let policy = load_authorized_keys(&repo, &mut gpg_ctx, &tag_commit, &config)?;
+
+    let branches = resolve_ref_patterns(&repo, patterns)?;
+
+    let mut commits = repo.revwalk()?;
+    for (_, target) in &branches {
+        commits.push(*target)?;
+    }
+    for oid in &hidden {
+        commits.hide(*oid)?;
+    }
+    commits.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+
+    // Each commit is verified once, even when reachable from several branches
+    let mut cache = VerificationCache::load(&repo, &mut gpg_ctx, &policy);
+    let mut results: HashMap<Oid, Option<VerifiedCommit>> = HashMap::new();
+    for oid in commits {
+        let oid = oid?;
+        let verified = match cache.signers(oid) {
+            Some(signers) => Some(VerifiedCommit {
+                oid,
+                signers: signers.to_vec(),
+                cached: true,
+            }),
+            None => match check_commit(&repo, &mut gpg_ctx, oid, &policy) {
+                Ok(checks) => checks.map(|checks| VerifiedCommit::new(oid, &checks)),
+                Err(_) => {
+                    eprintln!("🔴 Commit {} is not signed with GPG", oid);
+                    None
+                }
+            },
+        };
+        if let Some(verified) = &verified {
+            cache.add(oid, &verified.signers);
+        }
+        results.insert(oid, verified);
+    }
+    cache.save(&mut gpg_ctx)?;
+
+    let mut all_valid = true;
+    let mut trusted_branches = Vec::new();
+    let mut noted = Vec::new();
+    for (name, target) in &branches {
+        let mut branch_commits = repo.revwalk()?;
+        branch_commits.push(*target)?;
+        for oid in &hidden {
+            branch_commits.hide(*oid)?;
+        }
+
+        let mut branch_verified = Vec::new();
+        let mut branch_valid = true;
+        for oid in branch_commits {
+            match results.get(&oid?) {
+                Some(Some(verified)) => branch_verified.push(verified),
+                _ => branch_valid = false,
+            }
+        }
+
+        if branch_valid {
+            trusted_branches.push((name, target));
+            for verified in branch_verified {
+                if !noted
+                    .iter()
+                    .any(|noted: &VerifiedCommit| noted.oid == verified.oid)
+                {
+                    noted.push(verified.clone());
+                }
+            }
+        } else {
+            println!("🔴 {} has untrusted commits", name);
+            all_valid = false;
+        }
+    }
+
+    // Like the verification tag, markers only move once notes are recorded
+    if config.record_notes {
+        record_notes(&repo, &noted)?;
+    }
+    for (name, target) in trusted_branches {
+        let marker = marker_tag_name(name);
+        add_named_tag(&repo, &marker, &repo.find_commit(*target)?, None)?;
+        println!("✅ {} is trusted, tag {} moved to {}", name, marker, target);
+    }
 
     if all_valid {
         println!("🎉 All commits were signed and trusted.");
-        let to_commit = to_ref.peel_to_commit()?;
-        add_tag(&repo, &to_commit)?;
-        println!("Tag {} moved to {}", TAG_NAME, to_commit.id());
     }
 
     Ok(all_valid)
 }
 
+/// Verify a release tag, e.g. `v1.4.0`: its signature must be made by an authorized key,
+/// its tagger must be a user ID of the signing key (unless `git-sign-verifier.checktagger` is false),
+/// and commits between the verification tag and the tagged commit must be trusted.
+/// The verification tag is not moved.
+pub fn verify_tag_command(repo_path: &str, tag_name: &str) -> Result<bool, GitError> {
+    let repo = open_repo(repo_path);
+    let config = read_or_update_local_config(&repo, None)?;
+    let mut gpg_ctx = create_gpg_context(&config);
+
+    let Some(from_ref) = check_tag_exists(&repo) else {
+        return Err(GitError::from_str(&format!(
+            "Tag {} doesn't exist!",
+            TAG_NAME
+        )));
+    };
+    if !verify_tag(
+        &repo,
+        &mut gpg_ctx,
+        from_ref.target().unwrap(),
+        &config.signature_policy,
+    )? {
+        return Ok(false);
+    }
+
+    let tag_commit = from_ref.peel_to_commit()?;
+    let policy = load_authorized_keys(&repo, &mut gpg_ctx, &tag_commit, &config)?;
+
+    let release_ref = repo.find_reference(&format!("refs/tags/{}", tag_name))?;
+    if !verify_release_tag(
+        &repo,
+        &mut gpg_ctx,
+        &release_ref,
+        &policy,
+        config.check_tagger,
+    )? {
+        return Ok(false);
+    }
+
+    let all_valid = verify_from_ref(&repo, &from_ref, &release_ref, &mut gpg_ctx, &policy)?;
+
+    if all_valid {
+        println!(
+            "🎉 Tag {} and its commits are signed and trusted.",
+            tag_name
+        );
+    }
+
+    Ok(all_valid)
+}
+
+// Verify the signature and the tagger of a release tag
+fn verify_release_tag(
+    repo: &Repository,
+    gpg_ctx: &mut Context,
+    release_ref: &Reference,
+    policy: &SignaturePolicy,
+    check_tagger: bool,
+) -> Result<bool, GitError> {
+    let name = release_ref.shorthand().unwrap_or("");
+    let oid = release_ref.target().unwrap();
+    let object = repo.find_object(oid, None)?;
+
+    let Some(tag) = object.as_tag() else {
+        eprintln!(
+            "🔴 Tag {} is a lightweight tag: impossible to verify its authenticity.",
+            name
+        );
+        return Ok(false);
+    };
+
+    let Some((tag_content, signature_data)) = split_signed_tag(repo, oid)? else {
+        eprintln!("🔴 Tag {} is not signed", name);
+        return Ok(false);
+    };
+
+    let text_to_verify_data = gpgme::Data::from_bytes(tag_content.into_bytes()).unwrap();
+    let Some(checks) =
+        check_detached_signature(&signature_data, text_to_verify_data, gpg_ctx, name, policy)?
+    else {
+        return Ok(false);
+    };
+
+    if !check_tagger {
+        return Ok(true);
+    }
+
+    let tagger_email = tag
+        .tagger()
+        .and_then(|tagger| tagger.email().map(String::from))
+        .unwrap_or_default();
+
+    // Any valid signature made by a key of the tagger is enough
+    let tagger_signed = checks.iter().filter(|check| check.is_valid()).any(|check| {
+        gpg_ctx
+            .get_key(&check.fingerprint)
+            .map(|key| {
+                key.user_ids()
+                    .any(|uid| uid.email().is_ok_and(|email| email == tagger_email))
+            })
+            .unwrap_or(false)
+    });
+
+    if !tagger_signed {
+        eprintln!(
+            "🔴 Tagger <{}> of {} is not a user ID of the signing key",
+            tagger_email, name
+        );
+    }
+
+    Ok(tagger_signed)
+}
+
+// Branches matching patterns: globs of full ref names (`refs/heads/release/*`)
+// or short names (`main`). Returns the short name and target commit of each branch.
+fn resolve_ref_patterns(
+    repo: &Repository,
+    patterns: &[String],
+) -> Result<Vec<(String, Oid)>, GitError> {
+    let mut branches: Vec<(String, Oid)> = Vec::new();
+
+    for pattern in patterns {
+        let references = if pattern.starts_with("refs/") {
+            repo.references_glob(pattern)?
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            vec![repo.resolve_reference_from_short_name(pattern)?]
+        };
+
+        if references.is_empty() {
+            return Err(GitError::from_str(&format!("No ref matches {}", pattern)));
+        }
+
+        for reference in references {
+            let name = reference.shorthand().unwrap_or(pattern).to_string();
+            let target = reference.peel_to_commit()?.id();
+
+            if !branches.iter().any(|(known, _)| *known == name) {
+                branches.push((name, target));
+            }
+        }
+    }
+
+    Ok(branches)
+}
+
+/// Import the authorized keys file of a commit into the keyring,
+/// and return the signature policy of commits trusted by this file.
+pub fn load_authorized_keys(
+    repo: &Repository,
+    gpg_ctx: &mut Context,
+    commit: &Commit,
+    config: &Config,
+) -> Result<SignaturePolicy, GitError> {
+    let authorized_keys_content = read_authorized_keys_file(repo, commit)?;
+
+    let import_report = import_authorized_keys(gpg_ctx, &authorized_keys_content);
+    report_import(&import_report);
+
+    if config.strict_import && !import_report.failures.is_empty() {
+        return Err(GitError::from_str(&format!(
+            "{} block(s) of {} could not be imported (strict import)",
+            import_report.failures.len(),
+            AUTHORIZED_KEYS_FILE
+        )));
+    }
+
+    let mut policy = config.signature_policy.clone();
+    policy.authorized_subkeys = AuthorizedKeys::parse(&authorized_keys_content).authorized_subkeys;
+    policy.key_set = Some(key_set_id(&authorized_keys_content, &policy));
+
+    // Signatures made with non compliant keys will be rejected:
+    // warn early so that the authorized keys file can be fixed.
+    warn_non_compliant_keys(gpg_ctx, &import_report.fingerprints, &policy);
+
+    Ok(policy)
+}
+
+// Keys which failed to import would later show up as unknown keys:
+// report them now, with the reason given by GPG.
+fn report_import(import_report: &ImportReport) {
+    println!("🔑 Authorized keys: {}", import_report);
+
+    if import_report.secret > 0 {
+        eprintln!(
+            "⚠️ {} contains secret key material, which should never be committed",
+            AUTHORIZED_KEYS_FILE
+        );
+    }
+
+    for (line, reason) in &import_report.failures {
+        match line {
+            Some(line) => eprintln!(
+                "🔴 Block at line {} of {} could not be imported: {}",
+                line, AUTHORIZED_KEYS_FILE, reason
+            ),
+            None => eprintln!(
+                "🔴 {} could not be imported: {}",
+                AUTHORIZED_KEYS_FILE, reason
+            ),
+        }
+    }
+}
+
+fn warn_non_compliant_keys(
+    gpg_ctx: &mut Context,
+    fingerprints: &[String],
+    policy: &SignaturePolicy,
+) {
+    for fingerprint in fingerprints {
+        if let Ok(key) = gpg_ctx.get_key(fingerprint) {
+            for problem in policy.algorithms.check_key(&key) {
+                eprintln!(
+                    "⚠️ Authorized key {} does not comply with algorithm policy: {}",
+                    fingerprint, problem
+                );
+            }
+        }
+    }
+}
+
+fn signed_commit_data(commit: &Commit) -> gpgme::Result<gpgme::Data<'static>> {
+    gpgme::Data::from_bytes(signed_commit_payload(commit))
+}
+
 // In order to verify a signature, we have to construct the payload signed.
 // It's composed from the commit headers (except the signature) and the commit message as body.
 // Basically we iterate on headers and collect them in a buffer, then we concat the body message.
 // Work with bytes to deal with potential encoding issues.
-fn signed_commit_data(commit: &Commit) -> gpgme::Result<gpgme::Data<'static>> {
+pub fn signed_commit_payload(commit: &Commit) -> Vec<u8> {
     let raw_header_bytes = commit.raw_header_bytes();
     let mut filtered_header_bytes = Vec::new();
 
@@ -121,26 +547,63 @@ fn signed_commit_data(commit: &Commit) -> gpgme::Result<gpgme::Data<'static>> {
     payload_to_verify.push(b'\n');
     payload_to_verify.extend_from_slice(commit.message_raw_bytes());
 
-    gpgme::Data::from_bytes(&payload_to_verify)
+    payload_to_verify
+}
+
+/// Commit trusted during a verification, with the fingerprints of the keys
+/// which made its valid signatures.
+#[derive(Clone)]
+pub struct VerifiedCommit {
+    pub oid: Oid,
+    pub signers: Vec<String>,
+    /// Whether the commit was found in the verification cache rather than verified by this run.
+    pub cached: bool,
+}
+
+impl VerifiedCommit {
+    fn new(oid: Oid, checks: &[SignatureCheck]) -> VerifiedCommit {
+        VerifiedCommit {
+            oid,
+            signers: checks
+                .iter()
+                .filter(|check| check.is_valid())
+                .map(|check| check.fingerprint.clone())
+                .collect(),
+            cached: false,
+        }
+    }
+}
+
+/// Result of the verification of a range of commits.
+pub struct RangeVerification {
+    pub valid: bool,
+    /// Trusted commits of the range, including those found in the cache.
+    pub verified: Vec<VerifiedCommit>,
 }
 
 // Verify all commits are trusted between two references
-fn verify_from_ref(
+pub fn verify_from_ref(
     repo: &Repository,
     from_ref: &Reference,
     to_ref: &Reference,
     gpg_ctx: &mut Context,
+    policy: &SignaturePolicy,
 ) -> Result<bool, GitError> {
-    let mut commits = repo.revwalk()?;
+    check_from_ref(repo, from_ref, to_ref, gpg_ctx, policy).map(|result| result.valid)
+}
+
+// Same as `verify_from_ref`, returning the verified commits
+pub fn check_from_ref(
+    repo: &Repository,
+    from_ref: &Reference,
+    to_ref: &Reference,
+    gpg_ctx: &mut Context,
+    policy: &SignaturePolicy,
+) -> Result<RangeVerification, GitError> {
     let from_oid = from_ref.target().unwrap(); // tag oid
     let from_commit_oid = from_ref.peel_to_commit().unwrap().id(); // commit oid
     let to_oid = to_ref.target().unwrap(); // commit (HEAD) oid
 
-    let range_str = format!("{}..{}", from_oid, to_oid);
-    commits.push_range(&range_str)?;
-    commits.set_sorting(git2::Sort::TOPOLOGICAL)?;
-    commits.set_sorting(git2::Sort::REVERSE)?;
-
     println!(
         "Verifying commits from {from_ref}={from_oid} to {to_ref}={to_oid}",
         from_ref = from_ref.shorthand().unwrap(),
@@ -149,28 +612,103 @@ fn verify_from_ref(
         to_oid = to_oid,
     );
 
+    check_range(repo, from_oid, to_oid, gpg_ctx, policy)
+}
+
+// Verify all commits are trusted between two objects (commits or tags).
+// Commits found in the verification cache are not verified again.
+pub fn verify_range(
+    repo: &Repository,
+    from_oid: Oid,
+    to_oid: Oid,
+    gpg_ctx: &mut Context,
+    policy: &SignaturePolicy,
+) -> Result<bool, GitError> {
+    check_range(repo, from_oid, to_oid, gpg_ctx, policy).map(|result| result.valid)
+}
+
+// Same as `verify_range`, returning the verified commits
+pub fn check_range(
+    repo: &Repository,
+    from_oid: Oid,
+    to_oid: Oid,
+    gpg_ctx: &mut Context,
+    policy: &SignaturePolicy,
+) -> Result<RangeVerification, GitError> {
+    let mut commits = repo.revwalk()?;
+
+    let range_str = format!("{}..{}", from_oid, to_oid);
+    commits.push_range(&range_str)?;
+    commits.set_sorting(git2::Sort::TOPOLOGICAL)?;
+    commits.set_sorting(git2::Sort::REVERSE)?;
+
+    let mut cache = VerificationCache::load(repo, gpg_ctx, policy);
+    let mut result = RangeVerification {
+        valid: true,
+        verified: Vec::new(),
+    };
+    let mut cached = 0;
+
     for oid in commits {
         let commit_oid = oid.unwrap();
 
-        match verify_commit(&repo, gpg_ctx, commit_oid) {
-            Ok(true) => continue,
-            Ok(false) => return Ok(false),
+        if let Some(signers) = cache.signers(commit_oid) {
+            cached += 1;
+            result.verified.push(VerifiedCommit {
+                oid: commit_oid,
+                signers: signers.to_vec(),
+                cached: true,
+            });
+            continue;
+        }
+
+        match check_commit(repo, gpg_ctx, commit_oid, policy) {
+            Ok(Some(checks)) => {
+                let verified = VerifiedCommit::new(commit_oid, &checks);
+                cache.add(commit_oid, &verified.signers);
+                result.verified.push(verified);
+            }
+            Ok(None) => {
+                result.valid = false;
+                break;
+            }
             Err(_) => {
                 eprintln!("🔴 Commit {} is not signed with GPG", commit_oid);
-                return Ok(false);
+                result.valid = false;
+                break;
             }
         }
     }
 
-    Ok(true)
+    cache.save(gpg_ctx)?;
+
+    if cached > 0 {
+        println!(
+            "✅ {} commit(s) already verified with these keys (cache)",
+            cached
+        );
+    }
+
+    Ok(result)
 }
 
 // Verify signature of a single commit oid given a GPG context
-fn verify_commit(
+pub fn verify_commit(
     repo: &Repository,
     gpg_ctx: &mut Context,
     commit_oid: Oid,
+    policy: &SignaturePolicy,
 ) -> Result<bool, GitError> {
+    check_commit(repo, gpg_ctx, commit_oid, policy).map(|checks| checks.is_some())
+}
+
+// Same as `verify_commit`, returning the checks of the signatures of a trusted commit
+pub fn check_commit(
+    repo: &Repository,
+    gpg_ctx: &mut Context,
+    commit_oid: Oid,
+    policy: &SignaturePolicy,
+) -> Result<Option<Vec<SignatureCheck>>, GitError> {
     let commit = repo.find_commit(commit_oid)?;
     // Note: GPG and SSH signature are under gpgsig header!
     match commit.header_field_bytes("gpgsig") {
@@ -178,16 +716,17 @@ fn verify_commit(
             let signature_str = signature_data.as_str().unwrap_or("");
             let text_to_verify_data = signed_commit_data(&commit).unwrap();
 
-            match verify_detached_signature(
+            match check_detached_signature(
                 signature_str,
                 text_to_verify_data,
                 gpg_ctx,
                 &commit_oid.to_string(),
+                policy,
             ) {
-                Ok(true) => Ok(true),
-                Ok(false) => {
+                Ok(Some(checks)) => Ok(Some(checks)),
+                Ok(None) => {
                     print_commit(&commit);
-                    Ok(false)
+                    Ok(None)
                 }
                 Err(e) => Err(e),
             }
@@ -196,52 +735,104 @@ fn verify_commit(
     }
 }
 
-fn verify_tag(repo: &Repository, gpg_ctx: &mut Context, oid: Oid) -> Result<bool, GitError> {
+// A marker is trusted when it was created for its ref, and signed by the verifier:
+// keys of the keyring without a secret key, e.g. developer keys, may not sign markers.
+fn verify_marker(
+    repo: &Repository,
+    gpg_ctx: &mut Context,
+    marker: &Reference,
+    policy: &SignaturePolicy,
+) -> Result<bool, GitError> {
+    let name = marker.shorthand().unwrap_or("");
+    let oid = marker.target().unwrap();
+
+    let Ok(tag) = repo.find_tag(oid) else {
+        eprintln!("🔴 Marker {} is not an annotated tag", name);
+        return Ok(false);
+    };
+    if tag.name() != Some(name) {
+        eprintln!(
+            "🔴 Marker {} was created as {}",
+            name,
+            tag.name().unwrap_or("")
+        );
+        return Ok(false);
+    }
+
+    let Some((tag_content, signature_data)) = split_signed_tag(repo, oid)? else {
+        eprintln!("🔴 Signature not found in marker {}", name);
+        return Ok(false);
+    };
+    let checks = check_detached_signature(
+        &signature_data,
+        gpgme::Data::from_bytes(tag_content.into_bytes()).unwrap(),
+        gpg_ctx,
+        &oid.to_string(),
+        &SignaturePolicy {
+            rule: SignatureRule::Any,
+            ..policy.clone()
+        },
+    )?;
+
+    let signed_by_verifier = checks.is_some_and(|checks| {
+        checks
+            .iter()
+            .filter(|check| check.is_valid())
+            .any(|check| gpg_ctx.get_secret_key(&check.fingerprint).is_ok())
+    });
+    if !signed_by_verifier {
+        eprintln!("🔴 Marker {} is not signed by the verifier key", name);
+    }
+
+    Ok(signed_by_verifier)
+}
+
+pub fn verify_tag(
+    repo: &Repository,
+    gpg_ctx: &mut Context,
+    oid: Oid,
+    policy: &SignaturePolicy,
+) -> Result<bool, GitError> {
+    check_tag(repo, gpg_ctx, oid, policy).map(|checks| checks.is_some())
+}
+
+// Same as `verify_tag`, returning the checks of the signatures of a trusted tag
+pub fn check_tag(
+    repo: &Repository,
+    gpg_ctx: &mut Context,
+    oid: Oid,
+    policy: &SignaturePolicy,
+) -> Result<Option<Vec<SignatureCheck>>, GitError> {
     let object = repo.find_object(oid, None)?;
 
     match object.kind() {
         Some(ObjectType::Tag) => {
             let tag = object.as_tag().unwrap();
 
-            // Get raw tag data from Object Database
-            let odb = repo.odb()?;
-            let odb_object = odb.read(oid)?;
-            let raw_tag_data = odb_object.data();
-
-            // Tag data is structured like this:
-            // object 054b5abcdef
-            // type commit
-            // tag SIGN_VERIFIED
-            // tagger Test User <test@example.com> 1750782139 +0200
-            //
-            // The message text
-            // -----BEGIN PGP SIGNATURE-----
-            // iQIzBAABCAAdFiEE3MxljV1HemvIj+0nT7hl/bykvMQFAl6+8/0ACgkQT7hl/byk
-            // =kw8E
-            // -----END PGP SIGNATURE-----
-
-            // Convert raw data to string to find signature
-            let raw_tag_str = std::str::from_utf8(raw_tag_data)
-                .map_err(|e| GitError::from_str(&format!("Invalid UTF-8 in tag: {}", e)))?;
-
-            if let Some(sig_start_pos) = raw_tag_str.find("-----BEGIN") {
-                // Split at signature start
-                let (tag_content, signature_data) = raw_tag_str.split_at(sig_start_pos);
-
-                let text_to_verify_data = gpgme::Data::from_bytes(tag_content.as_bytes()).unwrap();
-
-                verify_detached_signature(
-                    signature_data,
-                    text_to_verify_data,
-                    gpg_ctx,
-                    &oid.to_string(),
-                )
-            } else {
-                eprintln!(
-                    "🔴 Signature not found in annotated tag. {}",
-                    tag.message().unwrap_or("")
-                );
-                Ok(false)
+            match split_signed_tag(repo, oid)? {
+                Some((tag_content, signature_data)) => {
+                    let text_to_verify_data =
+                        gpgme::Data::from_bytes(tag_content.into_bytes()).unwrap();
+
+                    check_detached_signature(
+                        &signature_data,
+                        text_to_verify_data,
+                        gpg_ctx,
+                        &oid.to_string(),
+                        // The tag is signed by the verifier key only
+                        &SignaturePolicy {
+                            rule: SignatureRule::Any,
+                            ..policy.clone()
+                        },
+                    )
+                }
+                None => {
+                    eprintln!(
+                        "🔴 Signature not found in annotated tag. {}",
+                        tag.message().unwrap_or("")
+                    );
+                    Ok(None)
+                }
             }
         }
         _ => {
@@ -249,48 +840,82 @@ fn verify_tag(repo: &Repository, gpg_ctx: &mut Context, oid: Oid) -> Result<bool
                 "🔴 Lightweight tag or tag not signed: impossible to verify its authenticity. {}",
                 oid
             );
-            Ok(false)
+            Ok(None)
         }
     }
 }
 
-// Helper function to verify a detached signature
-fn verify_detached_signature(
+// Split a raw signed tag into the signed content and its signature.
+fn split_signed_tag(repo: &Repository, oid: Oid) -> Result<Option<(String, String)>, GitError> {
+    // Get raw tag data from Object Database
+    let odb = repo.odb()?;
+    let odb_object = odb.read(oid)?;
+    let raw_tag_data = odb_object.data();
+
+    // Tag data is structured like this:
+    // object 054b5abcdef
+    // type commit
+    // tag SIGN_VERIFIED
+    // tagger Test User <test@example.com> 1750782139 +0200
+    //
+    // The message text
+    // -----BEGIN PGP SIGNATURE-----
+    // iQIzBAABCAAdFiEE3MxljV1HemvIj+0nT7hl/bykvMQFAl6+8/0ACgkQT7hl/byk
+    // =kw8E
+    // -----END PGP SIGNATURE-----
+
+    // Convert raw data to string to find signature
+    let raw_tag_str = std::str::from_utf8(raw_tag_data)
+        .map_err(|e| GitError::from_str(&format!("Invalid UTF-8 in tag: {}", e)))?;
+
+    // The signature is appended last: the message may contain anything
+    Ok(raw_tag_str.rfind("-----BEGIN").map(|sig_start_pos| {
+        let (tag_content, signature_data) = raw_tag_str.split_at(sig_start_pos);
+        (tag_content.to_string(), signature_data.to_string())
+    }))
+}
+
+// Verify a detached signature and return the checks of its signatures
+// when the signature rule is met.
+fn check_detached_signature(
     signature_str: &str,
     text_to_verify_data: gpgme::Data,
     gpg_ctx: &mut Context,
     identifier: &str,
-) -> Result<bool, GitError> {
+    policy: &SignaturePolicy,
+) -> Result<Option<Vec<SignatureCheck>>, GitError> {
     let signature_begin = signature_str.lines().next().unwrap_or("");
 
     if signature_begin == "-----BEGIN PGP SIGNATURE-----" {
         match gpg_ctx.verify_detached(signature_str, text_to_verify_data) {
-            Ok(verification_result) => match verify_gpg_signature_result(verification_result) {
-                Ok(()) => {
-                    println!("✅ Ref {} GPG signature is trusted", identifier);
-                    Ok(true)
-                }
-                Err(e) => {
-                    eprintln!("🔴 {} GPG signature is invalid: {}", identifier, e);
-                    Ok(false)
+            Ok(verification_result) => {
+                match verify_gpg_signature_result(verification_result, gpg_ctx, policy) {
+                    Ok(checks) => {
+                        println!("✅ Ref {} GPG signature is trusted", identifier);
+                        Ok(Some(checks))
+                    }
+                    Err(e) => {
+                        eprintln!("🔴 {} GPG signature is invalid: {}", identifier, e);
+                        Ok(None)
+                    }
                 }
-            },
+            }
             Err(e) => {
                 eprintln!(
                     "⚠️ Error in GPG signature verification for reference {}. Error: {}",
                     identifier, e
                 );
-                Ok(false)
+                Ok(None)
             }
         }
     } else if signature_begin == "-----BEGIN SSH SIGNATURE-----" {
         eprintln!("⚠️ Unsupported SSH signature on reference {}", identifier);
-        Ok(false)
+        Ok(None)
     } else {
         eprintln!(
             "⚠️ Unknown signature type on reference {}: (first line is `{}`)",
             identifier, signature_begin
         );
-        Ok(false)
+        Ok(None)
     }
 }
@@ -7,17 +7,14 @@ pub fn extract_tar_archive(archive_path: &Path, dest_dir: &Path) -> std::io::Res
 
     let output = Command::new("tar")
         .current_dir(dest_dir)
-        .args(&["xf", archive_path.to_str().unwrap()])
+        .args(["xf", archive_path.to_str().unwrap()])
         .output()?;
 
     if !output.status.success() {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!(
-                "Failed to extract tar archive: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ),
-        ));
+        return Err(std::io::Error::other(format!(
+            "Failed to extract tar archive: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
     }
 
     Ok(())
@@ -56,6 +53,303 @@ pub fn copy_directory(src: &Path, dst: &Path) -> std::io::Result<()> {
 pub fn kill_gpg_agent(gpg_home: &Path) {
     let _ = Command::new("gpgconf")
         .env("GNUPGHOME", gpg_home.to_str().unwrap())
-        .args(&["--kill", "gpg-agent"])
+        .args(["--kill", "gpg-agent"])
         .output();
 }
+
+/// Create a GPG home holding the secret key used to sign commits of test repositories
+pub fn create_signing_home(dest: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(dest)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dest, fs::Permissions::from_mode(0o700))?;
+    }
+
+    let keys = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("user-test-example-keys.asc");
+
+    run_gpg(dest, &["--import", keys.to_str().unwrap()], None)?;
+
+    Ok(())
+}
+
+/// Generate a new signing key (`ed25519`, `rsa1024`…) in a GPG home and return its fingerprint
+pub fn generate_signing_key(gpg_home: &Path, uid: &str, algo: &str) -> std::io::Result<String> {
+    run_gpg(
+        gpg_home,
+        &[
+            "--passphrase",
+            "",
+            "--quick-gen-key",
+            uid,
+            algo,
+            "sign",
+            "never",
+        ],
+        None,
+    )?;
+
+    let output = run_gpg(
+        gpg_home,
+        &["--with-colons", "--list-secret-keys", uid],
+        None,
+    )?;
+
+    String::from_utf8_lossy(&output)
+        .lines()
+        .find(|line| line.starts_with("fpr:"))
+        .and_then(|line| line.split(':').nth(9))
+        .map(|fpr| fpr.to_string())
+        .ok_or_else(|| std::io::Error::other(format!("No fingerprint found for {}", uid)))
+}
+
+/// Add a new ed25519 signing subkey to a key and return the subkey fingerprint
+pub fn add_signing_subkey(gpg_home: &Path, primary_fingerprint: &str) -> std::io::Result<String> {
+    run_gpg(
+        gpg_home,
+        &[
+            "--passphrase",
+            "",
+            "--quick-add-key",
+            primary_fingerprint,
+            "ed25519",
+            "sign",
+            "never",
+        ],
+        None,
+    )?;
+
+    let output = run_gpg(
+        gpg_home,
+        &["--with-colons", "--list-keys", primary_fingerprint],
+        None,
+    )?;
+
+    // Subkeys are listed in creation order: the last fingerprint is the new one
+    String::from_utf8_lossy(&output)
+        .lines()
+        .filter(|line| line.starts_with("fpr:"))
+        .filter_map(|line| line.split(':').nth(9))
+        .next_back()
+        .map(|fpr| fpr.to_string())
+        .ok_or_else(|| std::io::Error::other("No subkey fingerprint found"))
+}
+
+/// Export an armored public key from a GPG home
+pub fn export_public_key(gpg_home: &Path, fingerprint: &str) -> std::io::Result<String> {
+    let output = run_gpg(gpg_home, &["--armor", "--export", fingerprint], None)?;
+
+    Ok(String::from_utf8_lossy(&output).to_string())
+}
+
+/// Import armored keys into a GPG home
+pub fn import_keys(gpg_home: &Path, armored_keys: &str) -> std::io::Result<()> {
+    run_gpg(gpg_home, &["--import"], Some(armored_keys.as_bytes()))?;
+
+    Ok(())
+}
+
+/// Write a file in the working tree of a repository and add it to the index
+pub fn stage_file(repo_path: &Path, file_path: &str, content: &str) -> std::io::Result<()> {
+    fs::write(repo_path.join(file_path), content)?;
+    run_git(repo_path, &["add", file_path], None)?;
+
+    Ok(())
+}
+
+/// Add the repository at `url` as a submodule at `path`, and stage it.
+/// Local URLs are allowed, as git refuses the file protocol for submodules by default.
+pub fn add_submodule(repo_path: &Path, url: &Path, path: &str) -> std::io::Result<()> {
+    run_git(
+        repo_path,
+        &[
+            "-c",
+            "protocol.file.allow=always",
+            "submodule",
+            "add",
+            "--quiet",
+            url.to_str().unwrap(),
+            path,
+        ],
+        None,
+    )?;
+
+    Ok(())
+}
+
+/// Create a commit of the index on top of HEAD, signed by every given key in a single detached signature.
+/// When `tamper` is set, the signed message is replaced after signing to simulate a forged commit.
+/// Returns the new commit id, HEAD is moved to it. On an unborn branch, the commit is a root commit.
+pub fn commit_signed_by(
+    repo_path: &Path,
+    gpg_home: &Path,
+    signers: &[&str],
+    message: &str,
+    tamper: Option<&str>,
+) -> std::io::Result<String> {
+    let tree = run_git(repo_path, &["write-tree"], None)?;
+    let parent = run_git(
+        repo_path,
+        &["rev-parse", "--verify", "--quiet", "HEAD"],
+        None,
+    )
+    .map(|parent| format!("parent {}\n", parent))
+    .unwrap_or_default();
+    let identity = "Test User <test@example.com> 1750000000 +0000";
+
+    let headers = format!(
+        "tree {}\n{}author {}\ncommitter {}\n",
+        tree, parent, identity, identity
+    );
+    let payload = format!("{}\n{}\n", headers, message);
+
+    let mut args = vec!["--armor", "--detach-sign"];
+    for signer in signers {
+        args.extend(["--local-user", signer]);
+    }
+    let signature = run_gpg(gpg_home, &args, Some(payload.as_bytes()))?;
+    let signature = String::from_utf8_lossy(&signature);
+
+    let commit = format!(
+        "{}gpgsig {}\n\n{}\n",
+        headers,
+        signature.trim_end().replace('\n', "\n "),
+        tamper.unwrap_or(message)
+    );
+
+    let oid = run_git(
+        repo_path,
+        &["hash-object", "-t", "commit", "-w", "--stdin"],
+        Some(commit.as_bytes()),
+    )?;
+    run_git(repo_path, &["update-ref", "HEAD", &oid], None)?;
+
+    Ok(oid)
+}
+
+/// Create an annotated tag on HEAD signed by a key, with the given tagger (`Name <email>`).
+/// Returns the tag object id.
+pub fn tag_signed_by(
+    repo_path: &Path,
+    gpg_home: &Path,
+    signer: &str,
+    name: &str,
+    tagger: &str,
+) -> std::io::Result<String> {
+    let target = run_git(repo_path, &["rev-parse", "HEAD"], None)?;
+    let content = format!(
+        "object {}\ntype commit\ntag {}\ntagger {} 1750000000 +0000\n\nRelease {}\n",
+        target, name, tagger, name
+    );
+
+    let signature = run_gpg(
+        gpg_home,
+        &["--armor", "--detach-sign", "--local-user", signer],
+        Some(content.as_bytes()),
+    )?;
+    let tag = format!("{}{}", content, String::from_utf8_lossy(&signature));
+
+    let oid = run_git(
+        repo_path,
+        &["hash-object", "-t", "tag", "-w", "--stdin"],
+        Some(tag.as_bytes()),
+    )?;
+    run_git(
+        repo_path,
+        &["update-ref", &format!("refs/tags/{}", name), &oid],
+        None,
+    )?;
+
+    Ok(oid)
+}
+
+/// Serve a fixed body to every HTTP request on a local port, returns the port
+pub fn serve_http(body: String) -> std::io::Result<u16> {
+    let response = format!(
+        "HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    );
+
+    serve_http_response("127.0.0.1:0", response.into_bytes())
+}
+
+/// Serve a fixed raw response to every HTTP request on `address`, returns the port
+pub fn serve_http_response(address: &str, response: Vec<u8>) -> std::io::Result<u16> {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind(address)?;
+    let port = listener.local_addr()?.port();
+
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(&response);
+        }
+    });
+
+    Ok(port)
+}
+
+fn run_gpg(gpg_home: &Path, args: &[&str], input: Option<&[u8]>) -> std::io::Result<Vec<u8>> {
+    let mut gpg_args = vec![
+        "--homedir",
+        gpg_home.to_str().unwrap(),
+        "--batch",
+        "--pinentry-mode",
+        "loopback",
+    ];
+    gpg_args.extend_from_slice(args);
+
+    run_command("gpg", None, &gpg_args, input)
+}
+
+fn run_git(repo_path: &Path, args: &[&str], input: Option<&[u8]>) -> std::io::Result<String> {
+    let output = run_command("git", Some(repo_path), args, input)?;
+
+    Ok(String::from_utf8_lossy(&output).trim().to_string())
+}
+
+fn run_command(
+    program: &str,
+    current_dir: Option<&Path>,
+    args: &[&str],
+    input: Option<&[u8]>,
+) -> std::io::Result<Vec<u8>> {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    if let Some(dir) = current_dir {
+        command.current_dir(dir);
+    }
+
+    let mut child = command.spawn()?;
+    if let Some(bytes) = input {
+        child.stdin.take().unwrap().write_all(bytes)?;
+    }
+    drop(child.stdin.take());
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "{} {:?} failed: {}",
+            program,
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    Ok(output.stdout)
+}